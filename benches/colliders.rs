@@ -0,0 +1,99 @@
+//! How much merging wall tiles into rectangles saves, on a big generated level.
+//! Run with `cargo bench --bench colliders`.
+//!
+//! The same level is loaded twice. The first keeps the merged hitboxes the loader makes,
+//! the second swaps them for one hitbox per tile, the way levels used to be loaded.
+use std::env;
+use std::time::{Duration, Instant};
+
+use ddu2020::components::{BoxCollider, ColliderShape, Renderable, Terrain};
+use ddu2020::level::{MAX_LEVEL_SIZE, TILE_SIZE};
+use ddu2020::physics::{add_box_collider, Layers};
+use ddu2020::world::Simulation;
+
+use image::{Rgba, RgbaImage};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use specs::{Join, WorldExt};
+
+const TICKS: u32 = 300;
+
+const WALL: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const WATER: Rgba<u8> = Rgba([0, 0, 255, 255]);
+const PLAYER: Rgba<u8> = Rgba([0, 255, 0, 255]);
+const ENEMY: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+// A walled in level as big as they get, with blocks of wall and ponds all over,
+// and a crowd of each team at opposite ends
+fn generate_level() -> RgbaImage {
+    let size = MAX_LEVEL_SIZE;
+    let mut rng = StdRng::seed_from_u64(2020);
+    let mut image = RgbaImage::from_pixel(size, size, Rgba([255, 255, 255, 255]));
+
+    for i in 0..size {
+        for &(x, y) in &[(i, 0), (i, size - 1), (0, i), (size - 1, i)] {
+            image.put_pixel(x, y, WALL);
+        }
+    }
+    for _ in 0..400 {
+        let (w, h) = (rng.gen_range(1, 12), rng.gen_range(1, 12));
+        let (x, y) = (rng.gen_range(1, size - w - 1), rng.gen_range(20, size - h - 20));
+        let colour = if rng.gen_bool(0.25) { WATER } else { WALL };
+        for (x, y) in (x..x + w).flat_map(|x| (y..y + h).map(move |y| (x, y))) {
+            image.put_pixel(x, y, colour);
+        }
+    }
+    for x in (8..size - 8).step_by(12) {
+        image.put_pixel(x, 8, PLAYER);
+        image.put_pixel(x, size - 9, ENEMY);
+    }
+
+    image
+}
+
+// Swap the merged hitboxes for one per wall and water tile
+fn unmerge(simulation: &mut Simulation) {
+    let world = simulation.specs_world_mut();
+
+    let merged: Vec<_> = (&world.entities(), &world.read_storage::<Terrain>(), !&world.read_storage::<Renderable>()).join()
+        .map(|(entity, _, _)| entity)
+        .collect();
+    world.delete_entities(&merged).unwrap();
+
+    let tiles: Vec<_> = (&world.entities(), &world.read_storage::<Terrain>(), &world.read_storage::<Renderable>()).join()
+        .filter(|(_, terrain, _)| matches!(terrain, Terrain::Wall | Terrain::Water))
+        .map(|(entity, terrain, _)| (entity, *terrain))
+        .collect();
+    for (entity, terrain) in tiles {
+        world.write_storage().insert(entity, ColliderShape::rect(TILE_SIZE, TILE_SIZE)).unwrap();
+        let layer = if terrain == Terrain::Water { Layers::WATER } else { Layers::WALL };
+        add_box_collider(entity, layer, world).unwrap();
+    }
+    world.maintain();
+}
+
+fn run(name: &str, mut simulation: Simulation) -> Duration {
+    let hitboxes = simulation.specs_world().read_storage::<BoxCollider>().join().count();
+
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        simulation.update();
+    }
+    let per_tick = start.elapsed() / TICKS;
+
+    println!("{:<10} {:>6} hitboxes {:>10.3?} per tick", name, hitboxes, per_tick);
+    per_tick
+}
+
+fn main() {
+    // Absolute paths aren't looked up in the level directory
+    let path = env::temp_dir().join("ddu2020-bench-colliders.png");
+    generate_level().save(&path).unwrap();
+
+    let merged = run("merged", Simulation::with_seed(&path, 1).unwrap());
+
+    let mut simulation = Simulation::with_seed(&path, 1).unwrap();
+    unmerge(&mut simulation);
+    let per_tile = run("per tile", simulation);
+
+    println!("{:.1}x faster", per_tile.as_secs_f64() / merged.as_secs_f64());
+}
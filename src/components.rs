@@ -1,8 +1,10 @@
 use ggez_goodies::{Point2, Vector2};
 
+use collider::{HbId, HbProfile, geom::{PlacedShape, Shape, v2}};
 use specs::*;
 use specs_derive::*;
 
+use crate::physics::Profile;
 use crate::sprites::SpriteId;
 
 // This file contains every component. Components are simply storage for some data,
@@ -21,11 +23,63 @@ pub struct Motion {
     pub velocity: Vector2,
 }
 
-use collider::HbId;
-// A box collider
-#[derive(Clone, Debug, Component)]
+// The shape of the entity's hitbox. It's centred on the entity's position, moved by the offset.
+#[derive(Copy, Clone, Debug, PartialEq, Component)]
 #[storage(VecStorage)]
-pub struct BoxCollider(pub HbId);
+pub enum ColliderShape {
+    Circle { diameter: f32, offset: Vector2 },
+    Rect { w: f32, h: f32, offset: Vector2 },
+}
+
+impl ColliderShape {
+    pub fn circle(diameter: f32) -> Self {
+        ColliderShape::Circle { diameter, offset: Vector2::zero() }
+    }
+
+    pub fn rect(w: f32, h: f32) -> Self {
+        ColliderShape::Rect { w, h, offset: Vector2::zero() }
+    }
+
+    pub fn with_offset(self, offset: Vector2) -> Self {
+        match self {
+            ColliderShape::Circle { diameter, .. } => ColliderShape::Circle { diameter, offset },
+            ColliderShape::Rect { w, h, .. } => ColliderShape::Rect { w, h, offset },
+        }
+    }
+
+    // How far the middle of the hitbox is from the entity's position
+    pub fn offset(&self) -> Vector2 {
+        match self {
+            ColliderShape::Circle { offset, .. } | ColliderShape::Rect { offset, .. } => *offset,
+        }
+    }
+
+    pub fn shape(&self) -> Shape {
+        match self {
+            ColliderShape::Circle { diameter, .. } => Shape::circle(*diameter as f64),
+            ColliderShape::Rect { w, h, .. } => Shape::rect(v2(*w as f64, *h as f64)),
+        }
+    }
+
+    // The hitbox shape for an entity at the position
+    pub fn place(&self, pos: Point2) -> PlacedShape {
+        let middle = pos + self.offset();
+        self.shape().place(v2(middle.x as f64, middle.y as f64))
+    }
+}
+
+// A box collider, with the profile its hitbox was added to the collider with.
+// Only add it with the functions in physics. Removing it, or deleting the entity, removes the hitbox.
+#[derive(Clone, Debug, Component)]
+#[storage(FlaggedStorage)]
+pub struct BoxCollider(pub Profile);
+
+impl BoxCollider {
+    // The id of the hitbox in the collider
+    pub fn id(&self) -> HbId {
+        self.0.id()
+    }
+}
 
 // A tag to enable redering for the entity
 #[derive(Clone, Debug, Component)]
@@ -39,29 +93,182 @@ pub enum Renderable {
     SpriteId(SpriteId),
 }
 
-// Friendly character
+// The kind of ground a level tile is made of.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Component)]
+#[storage(VecStorage)]
+pub enum Terrain {
+    // Blocks movement and sight
+    Wall,
+    // Blocks movement, but can be seen across
+    Water,
+    // Slows down anyone walking through it
+    Mud,
+    // Just for looks
+    Floor,
+}
+
+// Something that can be gathered.
+#[derive(Clone, Debug, Component)]
+#[storage(VecStorage)]
+pub struct ResourceNode {
+    pub amount: u32,
+}
+
+// A character that can be given actions. Only the ones on the player's team can be
+// selected and ordered around, the rest are controlled by AI.
 #[derive(Clone, Debug, Default)]
-pub struct Friendly {
+pub struct Unit {
     pub selected: bool,
     pub action: Action
 }
 
-impl Component for Friendly {
+impl Component for Unit {
     type Storage = FlaggedStorage<Self, VecStorage<Self>>;
 }
 
+// Which side an entity is fighting for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Component)]
+#[storage(VecStorage)]
+pub enum Team {
+    Player,
+    Enemy,
+}
+
 pub const WALK_SPEED: f32 = 60.0;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub enum Action {
+    #[default]
     Standby,
-    Goto(Point2),
-    //Attack(EntityId)??
+    // Walk through the waypoints in order, the last one is the destination.
+    // Closest is how near to the destination we've got so far, and stalled how many ticks ago that was.
+    Goto {
+        waypoints: Vec<Point2>,
+        closest: f32,
+        stalled: u32,
+    },
+    // Walk up to the entity and hit it until it dies, through the waypoints if it is out of range.
+    // The waypoints are only found again once the target leaves the cell it was in when they were
+    // found, or when repath_in runs out.
+    Attack {
+        target: Entity,
+        waypoints: Vec<Point2>,
+        target_cell: Option<(usize, usize)>,
+        repath_in: u32,
+    },
+    // Stand still and don't let anyone push us out of the way.
+    Hold,
+}
+
+impl Action {
+    // Start walking, without having got anywhere yet
+    pub fn goto(waypoints: Vec<Point2>) -> Self {
+        Action::Goto {
+            waypoints,
+            closest: f32::INFINITY,
+            stalled: 0,
+        }
+    }
+
+    // Go after the target, the way there is found on the first tick
+    pub fn attack(target: Entity) -> Self {
+        Action::Attack {
+            target,
+            waypoints: Vec::new(),
+            target_cell: None,
+            repath_in: 0,
+        }
+    }
+}
+
+pub const UNIT_MASS: f32 = 1.0;
+
+// How hard the entity is to push around when it bumps into others.
+// Entities without one weigh as much as a unit.
+#[derive(Copy, Clone, Debug, Component)]
+#[storage(VecStorage)]
+pub struct Mass(pub f32);
+
+impl Default for Mass {
+    fn default() -> Self {
+        Mass(UNIT_MASS)
+    }
+}
+
+// Hit points. The entity dies once they reach zero.
+#[derive(Clone, Debug, Component)]
+#[storage(VecStorage)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Health {
+            current: max,
+            max,
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+pub const WEAPON_RANGE: f32 = 32.0;
+pub const WEAPON_DAMAGE: f32 = 10.0;
+pub const WEAPON_COOLDOWN: f32 = 1.0;
+// Hits do anywhere from this much less to this much more than the weapon's damage
+pub const DAMAGE_SPREAD: f32 = 0.25;
+
+// Lets the entity attack. Range is measured between the centers of the two entities
+// and the cooldown is in seconds. Damage is what a hit does on average, see DAMAGE_SPREAD.
+#[derive(Clone, Debug, Component)]
+#[storage(VecStorage)]
+pub struct Weapon {
+    pub range: f32,
+    pub damage: f32,
+    pub cooldown: f32,
+    // Seconds left until the weapon can be used again
+    pub ready_in: f32,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Weapon {
+            range: WEAPON_RANGE,
+            damage: WEAPON_DAMAGE,
+            cooldown: WEAPON_COOLDOWN,
+            ready_in: 0.0,
+        }
+    }
+}
+
+pub const ENEMY_SIGHT_RADIUS: f32 = 150.0;
+
+// What an enemy is currently up to.
+#[derive(Copy, Clone, Debug)]
+pub enum AiState {
+    Idle,
+    Chase(Entity),
+}
+
+// Lets an enemy look for player units to go after.
+#[derive(Clone, Debug, Component)]
+#[storage(VecStorage)]
+pub struct EnemyAi {
+    pub state: AiState,
+    // Player units closer than this are attacked, and chased until they get farther away.
+    pub sight_radius: f32,
 }
 
-impl Default for Action {
+impl Default for EnemyAi {
     fn default() -> Self {
-        return Action::Standby;
+        EnemyAi {
+            state: AiState::Idle,
+            sight_radius: ENEMY_SIGHT_RADIUS,
+        }
     }
 }
 
@@ -70,6 +277,14 @@ pub fn register_components(specs_world: &mut World) {
     specs_world.register::<Position>();
     specs_world.register::<Motion>();
     specs_world.register::<BoxCollider>();
+    specs_world.register::<ColliderShape>();
     specs_world.register::<Renderable>();
-    specs_world.register::<Friendly>();
+    specs_world.register::<Unit>();
+    specs_world.register::<Health>();
+    specs_world.register::<Weapon>();
+    specs_world.register::<Mass>();
+    specs_world.register::<Team>();
+    specs_world.register::<EnemyAi>();
+    specs_world.register::<Terrain>();
+    specs_world.register::<ResourceNode>();
 }
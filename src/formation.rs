@@ -0,0 +1,150 @@
+//! Spreading a move order out over the selected units, so they don't all head for the same spot.
+use ggez_goodies::{Point2, Vector2};
+use specs::Entity;
+
+// Distance between the middles of neighbouring units in a formation
+pub const SPACING: f32 = 28.0;
+
+// The shape selected units line up in when they are sent somewhere.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Formation {
+    // As square as possible
+    #[default]
+    Grid,
+    // One wide row across the direction of travel
+    Line,
+    // An arrowhead pointing the way they are going
+    Wedge,
+}
+
+impl Formation {
+    pub const ALL: [Formation; 3] = [Formation::Grid, Formation::Line, Formation::Wedge];
+
+    pub fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    pub fn from_name(name: &str) -> Option<Formation> {
+        Self::ALL.iter().find(|formation| formation.name() == name).cloned()
+    }
+
+    // The formation after this one, going back to the first after the last
+    pub fn next(&self) -> Formation {
+        let i = Self::ALL.iter().position(|formation| formation == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    // Where each unit stands, as (forward, right) distances from the middle of the formation.
+    // Rows go from the front to the back, and every row from left to right.
+    fn rows(&self, count: usize) -> Vec<Vec<(f32, f32)>> {
+        let mut rows: Vec<Vec<(f32, f32)>> = Vec::new();
+
+        // A row of units side by side, centred on the direction of travel
+        let row = |forward: f32, width: usize| -> Vec<(f32, f32)> {
+            (0..width)
+                .map(|i| (forward, (i as f32 - (width - 1) as f32 / 2.0) * SPACING))
+                .collect()
+        };
+
+        match self {
+            Formation::Line => rows.push(row(0.0, count)),
+            Formation::Grid => {
+                let columns = (count as f32).sqrt().ceil().max(1.0) as usize;
+                let mut left = count;
+                while left > 0 {
+                    let width = left.min(columns);
+                    rows.push(row(-(rows.len() as f32) * SPACING, width));
+                    left -= width;
+                }
+            }
+            Formation::Wedge => {
+                // One unit at the tip, then a unit further back on each side for every row
+                if count > 0 {
+                    rows.push(vec![(0.0, 0.0)]);
+                }
+                let mut left = count.saturating_sub(1);
+                let mut rank = 1.0;
+                while left > 0 {
+                    let sides: &[f32] = if left >= 2 { &[-1.0, 1.0] } else { &[-1.0] };
+                    rows.push(sides.iter().map(|side| (-rank * SPACING, side * rank * SPACING)).collect());
+                    left -= sides.len();
+                    rank += 1.0;
+                }
+            }
+        }
+
+        // Put the middle of the formation where the order was given
+        let (forward, right) = rows.iter().flatten().fold((0.0, 0.0), |(f, r), (forward, right)| (f + forward, r + right));
+        let (forward, right) = (forward / count.max(1) as f32, right / count.max(1) as f32);
+
+        rows.into_iter()
+            .map(|row| row.into_iter().map(|(f, r)| (f - forward, r - right)).collect())
+            .collect()
+    }
+}
+
+// Pick a spot in the formation for every unit. The formation is centred on the target and
+// faces away from where the units are now. Returns the units with their destinations, in no
+// particular order.
+pub fn arrange(formation: Formation, units: &[(Entity, Point2)], target: Point2) -> Vec<(Entity, Point2)> {
+    if units.is_empty() {
+        return Vec::new();
+    }
+
+    // Face the way the group is travelling
+    let middle = units.iter().fold(Vector2::zero(), |sum, (_, pos)| sum + pos.to_vector()) / units.len() as f32;
+    let travel = target.to_vector() - middle;
+    let forward = if travel.length() > f32::EPSILON { travel.normalize() } else { Vector2::new(0.0, -1.0) };
+    let right = Vector2::new(-forward.y, forward.x);
+
+    let rows = formation.rows(units.len());
+
+    // The units furthest ahead take the front row, then each row is filled from left to right,
+    // so nobody has to cross in front of anyone else to get to their spot.
+    let mut units = units.to_vec();
+    units.sort_by(|(_, a), (_, b)| {
+        let ahead = |pos: &Point2| pos.to_vector().dot(forward);
+        ahead(b).total_cmp(&ahead(a))
+    });
+
+    let mut arranged = Vec::with_capacity(units.len());
+    let mut rest = &mut units[..];
+    for row in rows {
+        let (row_units, others) = rest.split_at_mut(row.len());
+        row_units.sort_by(|(_, a), (_, b)| a.to_vector().dot(right).total_cmp(&b.to_vector().dot(right)));
+
+        for ((entity, pos), (f, r)) in row_units.iter().zip(row) {
+            arranged.push((*entity, *pos, target + forward * f + right * r));
+        }
+        rest = others;
+    }
+
+    uncross(&mut arranged);
+    arranged.into_iter().map(|(entity, _, destination)| (entity, destination)).collect()
+}
+
+// Swap destinations between pairs of units for as long as that shortens the total walk.
+// Two paths that cross can always be made shorter by swapping, so none are left crossing.
+fn uncross(arranged: &mut [(Entity, Point2, Point2)]) {
+    let length = |from: Point2, to: Point2| (to - from).length();
+
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 0..arranged.len() {
+            for j in i + 1..arranged.len() {
+                let (start_i, a) = (arranged[i].1, arranged[i].2);
+                let (start_j, b) = (arranged[j].1, arranged[j].2);
+                let now = length(start_i, a) + length(start_j, b);
+                let swapped = length(start_i, b) + length(start_j, a);
+
+                // Small gains are rounding errors, and could swap back and forth forever
+                if swapped < now - 0.01 {
+                    arranged[i].2 = b;
+                    arranged[j].2 = a;
+                    improved = true;
+                }
+            }
+        }
+    }
+}
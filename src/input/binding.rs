@@ -4,15 +4,17 @@ use std::hash::Hash;
 
 use super::types::*;
 
-pub struct InputBinding<Buttons>
+pub struct InputBinding<Axes, Buttons>
 where
+    Axes: Hash + Eq + Clone,
     Buttons: Hash + Eq + Clone,
 {
-    bindings: HashMap<InputType, InputEffect<Buttons>>,
+    bindings: HashMap<InputType, InputEffect<Axes, Buttons>>,
 }
 
-impl<Buttons> InputBinding<Buttons>
+impl<Axes, Buttons> InputBinding<Axes, Buttons>
 where
+    Axes: Hash + Eq + Clone,
     Buttons: Hash + Eq + Clone,
 {
     pub fn new() -> Self {
@@ -21,6 +23,26 @@ where
         }
     }
 
+    /// Adds a key binding connecting the given keycode to the given
+    /// logical axis.
+    pub fn bind_key_to_axis(mut self, keycode: KeyCode, axis: Axes, positive: bool) -> Self {
+        self.bindings.insert(
+            InputType::KeyEvent(keycode),
+            InputEffect::Axis(axis.clone(), positive),
+        );
+        self
+    }
+
+    /// Makes the mouse wheel push the given logical axis. Scrolling away from
+    /// the user pushes it towards positive if `positive` is true.
+    pub fn bind_wheel_to_axis(mut self, axis: Axes, positive: bool) -> Self {
+        self.bindings.insert(
+            InputType::MouseWheel,
+            InputEffect::Axis(axis.clone(), positive),
+        );
+        self
+    }
+
     /// Adds a key binding connecting the given keycode to the given
     /// logical button.
     pub fn bind_key_to_button(mut self, keycode: KeyCode, button: Buttons) -> Self {
@@ -41,8 +63,41 @@ where
         self
     }
 
+    /// Binds a physical input to a logical one at runtime, replacing whatever
+    /// it was bound to before. Returns the old binding.
+    pub fn bind<T: Into<InputType>>(&mut self, input: T, effect: InputEffect<Axes, Buttons>) -> Option<InputEffect<Axes, Buttons>> {
+        self.bindings.insert(input.into(), effect)
+    }
+
+    /// Removes the binding of a physical input. Returns what it was bound to.
+    pub fn unbind<T: Into<InputType>>(&mut self, input: T) -> Option<InputEffect<Axes, Buttons>> {
+        self.bindings.remove(&input.into())
+    }
+
+    /// Moves the logical button to the given physical input, removing every
+    /// other input it was bound to.
+    pub fn rebind_button<T: Into<InputType>>(&mut self, button: Buttons, input: T) {
+        self.rebind(InputEffect::Button(button), input.into());
+    }
+
+    /// Moves one direction of the logical axis to the given physical input,
+    /// removing every other input it was bound to.
+    pub fn rebind_axis<T: Into<InputType>>(&mut self, axis: Axes, positive: bool, input: T) {
+        self.rebind(InputEffect::Axis(axis, positive), input.into());
+    }
+
+    fn rebind(&mut self, effect: InputEffect<Axes, Buttons>, input: InputType) {
+        self.bindings.retain(|_, bound| *bound != effect);
+        self.bindings.insert(input, effect);
+    }
+
+    /// Every physical input that is bound, and what it is bound to. In no particular order.
+    pub fn bindings(&self) -> impl Iterator<Item = (&InputType, &InputEffect<Axes, Buttons>)> {
+        self.bindings.iter()
+    }
+
     /// Takes an physical input type and turns it into a logical input type (keycode -> axis/button).
-    pub fn resolve<T: Into<InputType>>(&self, input: T) -> Option<InputEffect<Buttons>> {
+    pub fn resolve<T: Into<InputType>>(&self, input: T) -> Option<InputEffect<Axes, Buttons>> {
         self.bindings.get(&input.into()).cloned()
     }
 }
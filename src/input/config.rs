@@ -0,0 +1,234 @@
+//! Reading and writing key bindings from the controls file.
+//!
+//! Every line binds one key, mouse button or the mouse wheel to a button or axis of
+//! the game, and lines starting with `#` are comments:
+//!
+//! ```text
+//! mouse Left Left
+//! key Escape Quit
+//! key W PanY-
+//! wheel Zoom+
+//! ```
+//!
+//! Keys are named like the variants of `ggez::event::KeyCode`, and mouse buttons are
+//! `Left`, `Right`, `Middle` or `Other` followed by a number. Axes are followed by
+//! the direction the input pushes them in.
+use ggez::event::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::{error, fmt, io};
+
+use super::types::*;
+use super::{create_input_binding, Axis, Binding, Button, Event};
+
+#[derive(Debug)]
+pub enum BindingError {
+    Io(io::Error),
+    // Lines are counted from 1
+    BadLine(usize, String),
+    UnknownKey(usize, String),
+    UnknownMouseButton(usize, String),
+    UnknownAction(usize, String),
+    // The input was already bound on an earlier line
+    Duplicate(usize, String),
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BindingError::Io(err) => write!(f, "{}", err),
+            BindingError::BadLine(line, text) => write!(f, "can't read line {}: {:?}", line, text),
+            BindingError::UnknownKey(line, name) => write!(f, "unknown key {:?} on line {}", name, line),
+            BindingError::UnknownMouseButton(line, name) => {
+                write!(f, "unknown mouse button {:?} on line {}", name, line)
+            }
+            BindingError::UnknownAction(line, name) => write!(f, "unknown action {:?} on line {}", name, line),
+            BindingError::Duplicate(line, input) => write!(f, "{} is bound twice, again on line {}", input, line),
+        }
+    }
+}
+
+impl error::Error for BindingError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            BindingError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BindingError {
+    fn from(err: io::Error) -> Self {
+        BindingError::Io(err)
+    }
+}
+
+// Defines key_name and key_from_name for every key in the list
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        pub fn key_name(keycode: KeyCode) -> &'static str {
+            match keycode {
+                $(KeyCode::$key => stringify!($key),)*
+            }
+        }
+
+        pub fn key_from_name(name: &str) -> Option<KeyCode> {
+            match name {
+                $(stringify!($key) => Some(KeyCode::$key),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+key_names! {
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24,
+    Snapshot, Scroll, Pause, Insert, Home, Delete, End, PageDown, PageUp,
+    Left, Up, Right, Down, Back, Return, Space, Compose, Caret, Numlock,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    AbntC1, AbntC2, Add, Apostrophe, Apps, At, Ax, Backslash, Calculator, Capital, Colon,
+    Comma, Convert, Decimal, Divide, Equals, Grave, Kana, Kanji, LAlt, LBracket, LControl,
+    LShift, LWin, Mail, MediaSelect, MediaStop, Minus, Multiply, Mute, MyComputer,
+    NavigateForward, NavigateBackward, NextTrack, NoConvert, NumpadComma, NumpadEnter,
+    NumpadEquals, OEM102, Period, PlayPause, Power, PrevTrack, RAlt, RBracket, RControl,
+    RShift, RWin, Semicolon, Slash, Sleep, Stop, Subtract, Sysrq, Tab, Underline, Unlabeled,
+    VolumeDown, VolumeUp, Wake, WebBack, WebFavorites, WebForward, WebHome, WebRefresh,
+    WebSearch, WebStop, Yen, Copy, Paste, Cut,
+}
+
+pub fn mouse_button_name(button: MouseButton) -> String {
+    match button {
+        MouseButton::Left => "Left".to_string(),
+        MouseButton::Right => "Right".to_string(),
+        MouseButton::Middle => "Middle".to_string(),
+        MouseButton::Other(n) => format!("Other{}", n),
+    }
+}
+
+pub fn mouse_button_from_name(name: &str) -> Option<MouseButton> {
+    match name {
+        "Left" => Some(MouseButton::Left),
+        "Right" => Some(MouseButton::Right),
+        "Middle" => Some(MouseButton::Middle),
+        _ => name.strip_prefix("Other")?.parse().ok().map(MouseButton::Other),
+    }
+}
+
+// The input as it is written in the controls file, like "key Escape"
+fn input_name(input: InputType) -> String {
+    match input {
+        InputType::KeyEvent(keycode) => format!("key {}", key_name(keycode)),
+        InputType::MouseEvent(button) => format!("mouse {}", mouse_button_name(button)),
+        InputType::MouseWheel => "wheel".to_string(),
+    }
+}
+
+// What the input does as it is written in the controls file, like "Quit" or "PanX+"
+fn action_name(effect: &Event) -> String {
+    match effect {
+        InputEffect::Button(button) => button.name(),
+        InputEffect::Axis(axis, true) => format!("{}+", axis.name()),
+        InputEffect::Axis(axis, false) => format!("{}-", axis.name()),
+    }
+}
+
+fn action_from_name(name: &str) -> Option<Event> {
+    if let Some(axis) = name.strip_suffix('+') {
+        return Axis::from_name(axis).map(|axis| InputEffect::Axis(axis, true));
+    }
+    if let Some(axis) = name.strip_suffix('-') {
+        return Axis::from_name(axis).map(|axis| InputEffect::Axis(axis, false));
+    }
+    Button::from_name(name).map(InputEffect::Button)
+}
+
+// Every action the controls file accepts, in the order they are written
+fn all_actions() -> Vec<Event> {
+    let buttons = Button::ALL.iter().map(|button| InputEffect::Button(*button));
+    let axes = Axis::ALL.iter()
+        .flat_map(|axis| vec![InputEffect::Axis(*axis, true), InputEffect::Axis(*axis, false)]);
+
+    buttons.chain(axes).collect()
+}
+
+pub fn parse_bindings(text: &str) -> Result<Binding, BindingError> {
+    let mut binding = Binding::new();
+    // Which line every input was bound on
+    let mut bound_on = HashMap::new();
+
+    for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (input, action) = match words[..] {
+            ["key", key, action] => {
+                let keycode = key_from_name(key)
+                    .ok_or_else(|| BindingError::UnknownKey(number, key.to_string()))?;
+                (InputType::KeyEvent(keycode), action)
+            }
+            ["mouse", mouse, action] => {
+                let mouse_button = mouse_button_from_name(mouse)
+                    .ok_or_else(|| BindingError::UnknownMouseButton(number, mouse.to_string()))?;
+                (InputType::MouseEvent(mouse_button), action)
+            }
+            ["wheel", action] => (InputType::MouseWheel, action),
+            _ => return Err(BindingError::BadLine(number, line.to_string())),
+        };
+
+        let effect = action_from_name(action)
+            .ok_or_else(|| BindingError::UnknownAction(number, action.to_string()))?;
+
+        if bound_on.insert(input, number).is_some() {
+            return Err(BindingError::Duplicate(number, input_name(input)));
+        }
+        binding.bind(input, effect);
+    }
+
+    Ok(binding)
+}
+
+pub fn write_bindings(binding: &Binding) -> String {
+    let actions = all_actions();
+
+    let mut lines: Vec<(usize, String)> = binding.bindings()
+        .map(|(input, effect)| {
+            // Keep the lines in the same order every time
+            let order = actions.iter().position(|action| action == effect).unwrap_or(0);
+            (order, format!("{} {}", input_name(*input), action_name(effect)))
+        })
+        .collect();
+    lines.sort();
+
+    let mut text = String::from("# Controls. One binding per line: key <key> <action>, mouse <button> <action> or wheel <action>\n");
+    text += &format!("# Actions: {}\n", actions.iter().map(action_name).collect::<Vec<_>>().join(", "));
+    for (_, line) in lines {
+        text += &line;
+        text += "\n";
+    }
+
+    text
+}
+
+pub fn save_bindings<P: AsRef<Path>>(binding: &Binding, path: P) -> io::Result<()> {
+    if let Some(dir) = path.as_ref().parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, write_bindings(binding))
+}
+
+// Read the controls file, or write the default controls to it if there isn't one yet.
+pub fn load_bindings<P: AsRef<Path>>(path: P) -> Result<Binding, BindingError> {
+    if !path.as_ref().exists() {
+        let binding = create_input_binding();
+        save_bindings(&binding, path)?;
+        return Ok(binding);
+    }
+
+    parse_bindings(&fs::read_to_string(path)?)
+}
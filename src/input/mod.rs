@@ -5,24 +5,141 @@ use ggez_goodies::Point2;
 mod types;
 mod binding;
 mod state;
+mod config;
+
+pub use types::InputType;
+pub use config::{load_bindings, save_bindings, parse_bindings, write_bindings, BindingError};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Axis {
+    // Camera movement, positive is right and down
+    PanX,
+    PanY,
+    // Positive zooms in
+    Zoom,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::PanX, Axis::PanY, Axis::Zoom];
+
+    /// The name used for the axis in files, the same as the variant name.
+    pub fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    pub fn from_name(name: &str) -> Option<Axis> {
+        Self::ALL.iter().find(|axis| axis.name() == name).cloned()
+    }
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Button {
     Left,
     Right,
     Quit,
+    Confirm,
+    // Save the game to the quicksave file, or load it from there
+    QuickSave,
+    QuickLoad,
+    // Select a control group, or store the selection in it with ctrl held
+    Group1,
+    Group2,
+    Group3,
+    Group4,
+    Group5,
+    Group6,
+    Group7,
+    Group8,
+    Group9,
+    // Switch to the next formation for move orders
+    Formation,
+    // Make the selected units stand their ground
+    Hold,
+    // Show the outlines of hitboxes on top of everything, for debugging
+    Hitboxes,
+}
+
+impl Button {
+    pub const ALL: [Button; 18] = [
+        Button::Left,
+        Button::Right,
+        Button::Quit,
+        Button::Confirm,
+        Button::QuickSave,
+        Button::QuickLoad,
+        Button::Group1,
+        Button::Group2,
+        Button::Group3,
+        Button::Group4,
+        Button::Group5,
+        Button::Group6,
+        Button::Group7,
+        Button::Group8,
+        Button::Group9,
+        Button::Formation,
+        Button::Hold,
+        Button::Hitboxes,
+    ];
+
+    // The buttons of the control groups, in order
+    pub const GROUPS: [Button; 9] = [
+        Button::Group1,
+        Button::Group2,
+        Button::Group3,
+        Button::Group4,
+        Button::Group5,
+        Button::Group6,
+        Button::Group7,
+        Button::Group8,
+        Button::Group9,
+    ];
+
+    /// The name used for the button in files, the same as the variant name.
+    pub fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    pub fn from_name(name: &str) -> Option<Button> {
+        Self::ALL.iter().find(|button| button.name() == name).cloned()
+    }
 }
 
-pub type Binding = binding::InputBinding<Button>;
-pub type Event = types::InputEffect<Button>;
-pub type State = state::InputState<Button>;
+pub type Binding = binding::InputBinding<Axis, Button>;
+pub type Event = types::InputEffect<Axis, Button>;
+pub type State = state::InputState<Axis, Button>;
 
 /// Create the default keybindings for our input state.
-pub fn create_input_binding() -> binding::InputBinding<Button> {
+pub fn create_input_binding() -> binding::InputBinding<Axis, Button> {
     binding::InputBinding::new()
         .bind_mouse_to_button(MouseButton::Left, Button::Left)
         .bind_mouse_to_button(MouseButton::Right, Button::Right)
         .bind_key_to_button(KeyCode::Escape, Button::Quit)
+        .bind_key_to_button(KeyCode::Return, Button::Confirm)
+        .bind_key_to_button(KeyCode::F5, Button::QuickSave)
+        .bind_key_to_button(KeyCode::F9, Button::QuickLoad)
+        .bind_key_to_button(KeyCode::Key1, Button::Group1)
+        .bind_key_to_button(KeyCode::Key2, Button::Group2)
+        .bind_key_to_button(KeyCode::Key3, Button::Group3)
+        .bind_key_to_button(KeyCode::Key4, Button::Group4)
+        .bind_key_to_button(KeyCode::Key5, Button::Group5)
+        .bind_key_to_button(KeyCode::Key6, Button::Group6)
+        .bind_key_to_button(KeyCode::Key7, Button::Group7)
+        .bind_key_to_button(KeyCode::Key8, Button::Group8)
+        .bind_key_to_button(KeyCode::Key9, Button::Group9)
+        .bind_key_to_button(KeyCode::F, Button::Formation)
+        .bind_key_to_button(KeyCode::H, Button::Hold)
+        .bind_key_to_button(KeyCode::F3, Button::Hitboxes)
+        .bind_key_to_axis(KeyCode::Up, Axis::PanY, false)
+        .bind_key_to_axis(KeyCode::W, Axis::PanY, false)
+        .bind_key_to_axis(KeyCode::Down, Axis::PanY, true)
+        .bind_key_to_axis(KeyCode::S, Axis::PanY, true)
+        .bind_key_to_axis(KeyCode::Left, Axis::PanX, false)
+        .bind_key_to_axis(KeyCode::A, Axis::PanX, false)
+        .bind_key_to_axis(KeyCode::Right, Axis::PanX, true)
+        .bind_key_to_axis(KeyCode::D, Axis::PanX, true)
+        .bind_key_to_axis(KeyCode::Equals, Axis::Zoom, true)
+        .bind_key_to_axis(KeyCode::Minus, Axis::Zoom, false)
+        .bind_wheel_to_axis(Axis::Zoom, true)
 }
 
 pub struct MouseInput {
@@ -2,8 +2,32 @@ use std::hash::Hash;
 use std::collections::HashMap;
 
 use super::types::*;
+use ggez::event::KeyMods;
 use ggez_goodies::Point2;
 
+#[derive(Debug, Copy, Clone)]
+struct AxisState {
+    // Where the axis is, between -1.0 and 1.0
+    position: f32,
+    // Which way it is being pushed, -1.0, 0.0 or 1.0
+    direction: f32,
+    // How fast it moves while being pushed, per second
+    acceleration: f32,
+    // How fast it falls back to 0.0 when let go, per second
+    gravity: f32,
+}
+
+impl Default for AxisState {
+    fn default() -> Self {
+        AxisState {
+            position: 0.0,
+            direction: 0.0,
+            acceleration: 4.0,
+            gravity: 3.0,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Default)]
 struct ButtonState {
     // Current state
@@ -15,23 +39,31 @@ struct ButtonState {
 }
 
 #[derive(Debug, Clone)]
-pub struct InputState<Buttons>
+pub struct InputState<Axes, Buttons>
 where
+    Axes: Hash + Eq + Clone,
     Buttons: Hash + Eq + Clone,
 {
+    // Input states for axes
+    axes: HashMap<Axes, AxisState>,
     // Input states for buttons
     buttons: HashMap<Buttons, ButtonState>,
     mouse_position: Point2,
+    // Which of shift, ctrl, alt and logo are held down
+    modifiers: KeyMods,
 }
 
-impl<Buttons> InputState<Buttons>
+impl<Axes, Buttons> InputState<Axes, Buttons>
 where
+    Axes: Eq + Hash + Clone,
     Buttons: Eq + Hash + Clone,
 {
     pub fn new() -> Self {
         InputState {
+            axes: HashMap::new(),
             buttons: HashMap::new(),
             mouse_position: Point2::new(0.0, 0.0),
+            modifiers: KeyMods::NONE,
         }
     }
 
@@ -39,7 +71,18 @@ where
     /// physical input state.  Should be called in the update()
     /// handler before the input dependent update.
     /// So, it will do things like move the axes and so on.
-    pub fn update(&mut self) {
+    pub fn update(&mut self, dt: f32) {
+        for (_axis, axis_status) in self.axes.iter_mut() {
+            if axis_status.direction != 0.0 {
+                let vel = axis_status.acceleration * dt * axis_status.direction;
+                axis_status.position = (axis_status.position + vel).clamp(-1.0, 1.0);
+            } else {
+                // Fall back towards the middle, without overshooting it
+                let abs_dx = f32::min(axis_status.gravity * dt, axis_status.position.abs());
+                axis_status.position -= abs_dx * axis_status.position.signum();
+            }
+        }
+
         for (_button, button_status) in self.buttons.iter_mut() {
             button_status.pressed = false;
             button_status.released = false;
@@ -47,8 +90,20 @@ where
     }
 
     /// Takes an InputEffect and actually applies it.
-    pub fn update_effect(&mut self, effect: InputEffect<Buttons>, started: bool) {
+    pub fn update_effect(&mut self, effect: InputEffect<Axes, Buttons>, started: bool) {
         match effect {
+            InputEffect::Axis(axis, positive) => {
+                let f = || AxisState::default();
+                let axis_status = self.axes.entry(axis).or_insert_with(f);
+                let direction = if positive { 1.0 } else { -1.0 };
+
+                if started {
+                    axis_status.direction = direction;
+                } else if axis_status.direction == direction {
+                    // Only stop if the other direction wasn't pressed since
+                    axis_status.direction = 0.0;
+                }
+            }
             InputEffect::Button(button) => {
                 let f = || ButtonState::default();
                 let button_status = self.buttons.entry(button).or_insert_with(f);
@@ -62,6 +117,30 @@ where
         }
     }
 
+    /// Gives the axis a push, from something like the mouse wheel that
+    /// moves in steps instead of being held down. It falls back to 0.0
+    /// on its own afterwards.
+    pub fn update_axis_impulse(&mut self, axis: Axes, amount: f32) {
+        let f = || AxisState::default();
+        let axis_status = self.axes.entry(axis).or_insert_with(f);
+        axis_status.position = (axis_status.position + amount).clamp(-1.0, 1.0);
+    }
+
+    /// Returns where the axis is, between -1.0 and 1.0. It speeds up
+    /// while held and slows down when let go.
+    pub fn get_axis(&self, axis: Axes) -> f32 {
+        let d = AxisState::default();
+        let axis_status = self.axes.get(&axis).unwrap_or(&d);
+        axis_status.position
+    }
+
+    /// Returns which way the axis is being pushed right now, -1.0, 0.0 or 1.0.
+    pub fn get_axis_raw(&self, axis: Axes) -> f32 {
+        let d = AxisState::default();
+        let axis_status = self.axes.get(&axis).unwrap_or(&d);
+        axis_status.direction
+    }
+
     fn get_button(&self, button: Buttons) -> ButtonState {
         let d = ButtonState::default();
         let button_status = self.buttons.get(&button).unwrap_or(&d);
@@ -97,14 +176,24 @@ where
     pub fn mouse_position(&self) -> Point2 {
         self.mouse_position
     }
+
+    pub fn update_modifiers(&mut self, modifiers: KeyMods) {
+        self.modifiers = modifiers;
+    }
+
+    /// Returns the modifier keys that are held down right now.
+    pub fn modifiers(&self) -> KeyMods {
+        self.modifiers
+    }
 }
 
 // We implement Default so specs accepts this as a Read type in system data.
-impl<Buttons> Default for InputState<Buttons>
+impl<Axes, Buttons> Default for InputState<Axes, Buttons>
 where
+    Axes: Hash + Eq + Clone,
     Buttons: Hash + Eq + Clone,
 {
     fn default() -> Self {
         panic!("Input state is not supposed to be generated from default");
     }
-}
\ No newline at end of file
+}
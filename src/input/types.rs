@@ -4,7 +4,8 @@ use std::hash::Hash;
 #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
 pub enum InputType {
     KeyEvent(KeyCode),
-    MouseEvent(MouseButton)
+    MouseEvent(MouseButton),
+    MouseWheel,
 }
 
 impl From<KeyCode> for InputType {
@@ -20,9 +21,12 @@ impl From<MouseButton> for InputType {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
-pub enum InputEffect<Buttons>
+pub enum InputEffect<Axes, Buttons>
 where
+    Axes: Hash + Eq + Clone,
     Buttons: Hash + Eq + Clone,
 {
+    // Pushes the axis towards 1.0 if true, or -1.0 if false
+    Axis(Axes, bool),
     Button(Buttons),
-}
\ No newline at end of file
+}
@@ -1,45 +1,362 @@
+//! Levels are png images in `resources/rooms`, where every pixel becomes one tile.
+//! The colour of the pixel decides what the tile is:
+//!
+//! | Colour | RGB           | Tile                                          |
+//! |--------|---------------|-----------------------------------------------|
+//! | black  | 0, 0, 0       | Wall, blocks movement and sight               |
+//! | blue   | 0, 0, 255     | Water, blocks movement but not sight          |
+//! | brown  | 128, 64, 0    | Mud, units walk through it at half speed      |
+//! | grey   | 128, 128, 128 | Floor, only for decoration                    |
+//! | yellow | 255, 255, 0   | Resource node                                 |
+//! | green  | 0, 255, 0     | Player spawn, a player unit starts here       |
+//! | red    | 255, 0, 0     | Enemy spawn, an enemy unit starts here        |
+//! | white  | 255, 255, 255 | Empty ground                                  |
+//!
+//! Pixels have to be fully opaque, fully transparent pixels are empty ground as well.
+//! Any other colour is ignored, or refused when loading in strict mode.
+//!
+//! Every tile is drawn on its own, but neighbouring wall or water tiles share one
+//! rectangular hitbox, see `merge_walls`.
+use std::fs;
 use std::path::{Path, PathBuf};
-use std::fs::File;
+use std::{error, fmt, io};
 
 use specs::{world::Builder, World, WorldExt};
 use ggez_goodies::Point2;
-use image;
-use collider::geom::*;
 
-use crate::physics::add_box_collider;
+use crate::physics::{add_box_collider, ColliderRes, HitboxError, Layers};
 use crate::components::*;
+use crate::pathfinding::NavGrid;
 
-pub fn load_level<P: AsRef<Path>>(level: &P, world: &mut World) {
+// Where the level images are, relative to the working directory
+pub const LEVEL_DIR: &str = "resources/rooms";
+
+// Every pixel in a level image becomes a square tile this wide in the game world.
+pub const TILE_SIZE: f32 = 32.0;
+
+// Levels can't be wider or taller than this many tiles
+pub const MAX_LEVEL_SIZE: u32 = 256;
+
+// Walking speed multiplier in mud
+pub const MUD_SPEED: f32 = 0.5;
+
+// How much a resource node holds when the level starts
+pub const RESOURCE_AMOUNT: u32 = 500;
+
+// What a single pixel of the level image turns into.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tile {
+    Empty,
+    Wall,
+    Water,
+    Mud,
+    Floor,
+    Resource,
+    PlayerSpawn,
+    EnemySpawn,
+}
+
+impl Tile {
+    // Look up a pixel in the colour legend. Returns None for colours that aren't in it.
+    pub fn from_pixel(pixel: [u8; 4]) -> Option<Tile> {
+        match pixel {
+            [0, 0, 0, 255] => Some(Tile::Wall),
+            [0, 0, 255, 255] => Some(Tile::Water),
+            [128, 64, 0, 255] => Some(Tile::Mud),
+            [128, 128, 128, 255] => Some(Tile::Floor),
+            [255, 255, 0, 255] => Some(Tile::Resource),
+            [0, 255, 0, 255] => Some(Tile::PlayerSpawn),
+            [255, 0, 0, 255] => Some(Tile::EnemySpawn),
+            [255, 255, 255, 255] | [_, _, _, 0] => Some(Tile::Empty),
+            _ => None,
+        }
+    }
+}
+
+// A place where a unit should be created once the level is loaded.
+#[derive(Copy, Clone, Debug)]
+pub struct SpawnPoint {
+    pub team: Team,
+    pub pos: Point2,
+}
+
+// What the game needs to know about a level after it's been loaded into the world.
+#[derive(Clone, Debug)]
+pub struct Level {
+    // Size in tiles
+    pub width: usize,
+    pub height: usize,
+    pub spawn_points: Vec<SpawnPoint>,
+}
+
+// Everything that can go wrong when loading a level.
+#[derive(Debug)]
+pub enum LevelError {
+    // There is no level file with that name
+    NotFound(PathBuf),
+    // The file is there, but it isn't an image we can read
+    Unreadable(PathBuf, image::ImageError),
+    // A pixel isn't in the colour legend. Only reported when loading in strict mode.
+    UnknownColour {
+        x: u32,
+        y: u32,
+        pixel: [u8; 4],
+    },
+    // The level is bigger than MAX_LEVEL_SIZE in some direction
+    TooLarge {
+        width: u32,
+        height: u32,
+    },
+    // The world isn't ready for hitboxes to be added
+    NoCollider,
+    // Something the level creates couldn't be given a hitbox
+    Hitbox(HitboxError),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::NotFound(path) => write!(f, "no level at {}", path.display()),
+            LevelError::Unreadable(path, err) => write!(f, "couldn't read level {}: {}", path.display(), err),
+            LevelError::UnknownColour { x, y, pixel } => write!(
+                f,
+                "unknown colour {:?} at pixel ({}, {})",
+                pixel, x, y
+            ),
+            LevelError::TooLarge { width, height } => write!(
+                f,
+                "level is {}x{} tiles, the most allowed is {}x{}",
+                width, height, MAX_LEVEL_SIZE, MAX_LEVEL_SIZE
+            ),
+            LevelError::NoCollider => write!(f, "the collider hasn't been set up yet"),
+            LevelError::Hitbox(err) => write!(f, "couldn't add a hitbox: {}", err),
+        }
+    }
+}
+
+impl error::Error for LevelError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LevelError::Unreadable(_, err) => Some(err),
+            LevelError::Hitbox(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<HitboxError> for LevelError {
+    fn from(err: HitboxError) -> Self {
+        match err {
+            HitboxError::NoCollider => LevelError::NoCollider,
+            err => LevelError::Hitbox(err),
+        }
+    }
+}
+
+// Names of every level in resources/rooms, sorted.
+pub fn list_levels() -> io::Result<Vec<String>> {
+    let mut levels = Vec::new();
+
+    for entry in fs::read_dir(LEVEL_DIR)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png")) {
+            if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                levels.push(name.to_string());
+            }
+        }
+    }
+
+    levels.sort();
+    Ok(levels)
+}
+
+// Load a level from resources/rooms into the world. In strict mode, colours that aren't
+// in the legend are an error instead of being ignored.
+// The level is checked before anything is added, so the world is left untouched if it fails.
+pub fn load_level<P: AsRef<Path>>(level: &P, world: &mut World, strict: bool) -> Result<Level, LevelError> {
     // Locate
-    let mut path = PathBuf::from("resources/rooms");
+    let mut path = PathBuf::from(LEVEL_DIR);
     path.push(level);
 
+    if !path.is_file() {
+        return Err(LevelError::NotFound(path));
+    }
+
     // Read png file
-    let image = image::open(path).expect("No such level").to_rgba();
-    
+    let image = match image::open(&path) {
+        Ok(image) => image.to_rgba(),
+        Err(err) => return Err(LevelError::Unreadable(path, err)),
+    };
+
+    if image.width() > MAX_LEVEL_SIZE || image.height() > MAX_LEVEL_SIZE {
+        return Err(LevelError::TooLarge {
+            width: image.width(),
+            height: image.height(),
+        });
+    }
+
+    if strict {
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if Tile::from_pixel(pixel.0).is_none() {
+                return Err(LevelError::UnknownColour { x, y, pixel: pixel.0 });
+            }
+        }
+    }
+
+    let has_collider = world.try_fetch::<Option<ColliderRes>>()
+        .is_some_and(|collider| collider.is_some());
+    if !has_collider {
+        return Err(LevelError::NoCollider);
+    }
+
+    let (width, height) = (image.width() as usize, image.height() as usize);
+
+    // Walls and terrain are also marked in the navigation grid, so units can path around them
+    let mut nav_grid = NavGrid::new(width, height);
+    let mut spawn_points = Vec::new();
+    let mut tiles = Vec::with_capacity(width * height);
+
     // enumerat pixels
-    for (x, y, pixel) in image.enumerate_pixels() {        
-        match pixel.0 {
-            // Wall
-            [0, 0, 0, 255] => {
-                let (x, y) = (x as f64 * 32.0, y as f64 * 32.0);
-
-                let entity = world.create_entity()
-                    .with(Position(Point2::new(x as f32, y as f32)))
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let (x, y) = (x as usize, y as usize);
+        let pos = NavGrid::cell_center(x, y);
+        let tile = Tile::from_pixel(pixel.0).unwrap_or(Tile::Empty);
+        tiles.push(tile);
+
+        match tile {
+            Tile::Empty => (),
+            Tile::Wall => {
+                nav_grid.set_blocked(x, y, true);
+                add_tile(pos, Terrain::Wall, world);
+            }
+            Tile::Water => {
+                nav_grid.set_blocked(x, y, true);
+                add_tile(pos, Terrain::Water, world);
+            }
+            Tile::Mud => {
+                nav_grid.set_speed(x, y, MUD_SPEED);
+                add_tile(pos, Terrain::Mud, world);
+            }
+            Tile::Floor => add_tile(pos, Terrain::Floor, world),
+            Tile::Resource => {
+                world.create_entity()
+                    .with(Position(pos))
                     .with(Renderable::Rectangle {
-                        w: 32.0,
-                        h: 32.0,
-                        color: ggez::graphics::Color::new(0.25, 0.5, 0.25, 1.0)
+                        w: TILE_SIZE / 2.0,
+                        h: TILE_SIZE / 2.0,
+                        color: ggez::graphics::Color::new(1.0, 0.85, 0.0, 1.0)
                     })
+                    .with(ResourceNode { amount: RESOURCE_AMOUNT })
                     .build();
+            }
+            Tile::PlayerSpawn => spawn_points.push(SpawnPoint { team: Team::Player, pos }),
+            Tile::EnemySpawn => spawn_points.push(SpawnPoint { team: Team::Enemy, pos }),
+        }
+    }
 
-                let hitbox = Shape::square(32.0).place(v2(x, y)).still();
-                
-                add_box_collider(entity, hitbox, true, world);
+    // The tiles are only drawn, walls and water get one hitbox per rectangle of them instead
+    for rect in merge_walls(&tiles, width) {
+        add_wall(rect, world)?;
+    }
+
+    world.insert(nav_grid);
+
+    Ok(Level {
+        width,
+        height,
+        spawn_points,
+    })
+}
+
+// A rectangle of wall or water tiles that all share one hitbox. Position and size are in tiles.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WallRect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+    pub terrain: Terrain,
+}
+
+// Cover the wall and water tiles of a level with as few rectangles as we easily can.
+// Tiles are given row by row, `width` to a row. Going through them in order, each tile that
+// isn't covered yet starts a rectangle, which is made as wide as it goes and then as tall
+// as the whole width goes. Walls and water are never mixed in one rectangle.
+pub fn merge_walls(tiles: &[Tile], width: usize) -> Vec<WallRect> {
+    let blocking = |tile: Tile| match tile {
+        Tile::Wall => Some(Terrain::Wall),
+        Tile::Water => Some(Terrain::Water),
+        _ => None,
+    };
+
+    let height = tiles.len() / width;
+    let mut covered = vec![false; tiles.len()];
+    let mut rects = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            let terrain = match blocking(tiles[y * width + x]) {
+                Some(terrain) if !covered[y * width + x] => terrain,
+                _ => continue,
+            };
+            let fits = |x: usize, y: usize| !covered[y * width + x] && blocking(tiles[y * width + x]) == Some(terrain);
+
+            let mut w = 1;
+            while x + w < width && fits(x + w, y) {
+                w += 1;
+            }
+            let mut h = 1;
+            while y + h < height && (x..x + w).all(|x| fits(x, y + h)) {
+                h += 1;
             }
-            _ => (),
+
+            for row in y..y + h {
+                covered[row * width + x..row * width + x + w].iter_mut().for_each(|cell| *cell = true);
+            }
+            rects.push(WallRect { x, y, w, h, terrain });
         }
     }
+
+    rects
 }
 
+// Create the hitbox for a rectangle of walls or water, so nothing can walk through it.
+// It isn't drawn, the tiles underneath are.
+fn add_wall(rect: WallRect, world: &mut World) -> Result<(), HitboxError> {
+    let corner = NavGrid::cell_center(rect.x, rect.y);
+    let pos = Point2::new(
+        corner.x + (rect.w - 1) as f32 * TILE_SIZE / 2.0,
+        corner.y + (rect.h - 1) as f32 * TILE_SIZE / 2.0,
+    );
 
+    let entity = world.create_entity()
+        .with(Position(pos))
+        .with(ColliderShape::rect(rect.w as f32 * TILE_SIZE, rect.h as f32 * TILE_SIZE))
+        .with(rect.terrain)
+        .build();
+    let layer = match rect.terrain {
+        Terrain::Water => Layers::WATER,
+        _ => Layers::WALL,
+    };
+    add_box_collider(entity, layer, world)
+}
+
+// Create a terrain tile.
+fn add_tile(pos: Point2, terrain: Terrain, world: &mut World) {
+    let color = match terrain {
+        Terrain::Wall => ggez::graphics::Color::new(0.25, 0.5, 0.25, 1.0),
+        Terrain::Water => ggez::graphics::Color::new(0.1, 0.3, 0.8, 1.0),
+        Terrain::Mud => ggez::graphics::Color::new(0.4, 0.25, 0.1, 1.0),
+        Terrain::Floor => ggez::graphics::Color::new(0.3, 0.3, 0.3, 1.0),
+    };
+
+    world.create_entity()
+        .with(Position(pos))
+        .with(Renderable::Rectangle {
+            w: TILE_SIZE,
+            h: TILE_SIZE,
+            color
+        })
+        .with(terrain)
+        .build();
+}
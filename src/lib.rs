@@ -0,0 +1,16 @@
+//! The game simulation and rendering, split out from `main.rs` so the
+//! simulation can be driven without a window (tests, CI).
+pub mod input;
+pub mod world;
+pub mod systems;
+pub mod components;
+pub mod resources;
+pub mod sprites;
+pub mod level;
+pub mod physics;
+pub mod query;
+pub mod pathfinding;
+pub mod formation;
+pub mod replay;
+pub mod save;
+pub mod scenes;
@@ -2,16 +2,70 @@ use ggez::{self, *};
 
 use std::{env, path};
 
-mod input;
-mod world;
-mod systems;
-mod components;
-mod resources;
-mod sprites;
-mod level;
-mod physics;
+use ddu2020::{input, world, resources::TICKS_PER_SECOND, replay::Replay};
+use ddu2020::scenes::{self, level::LevelScene, menu::MainMenu};
+
+// Name of the controls file in the user config directory
+const BINDINGS_FILE: &str = "bindings.txt";
 
 fn main() {
+    // Usage: ddu2020 [level] [--strict] [--record <file>] [--replay <file>] [--load <file>]
+    // Without a level, replay or save the game starts in the main menu.
+    // With --strict, a level with colours that aren't in the legend isn't loaded at all.
+    let mut level = None;
+    let mut record = None;
+    let mut replay = None;
+    let mut load = None;
+    let mut strict = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => record = args.next(),
+            "--replay" => replay = args.next(),
+            "--load" => load = args.next(),
+            "--strict" => strict = true,
+            _ => level = Some(arg),
+        }
+    }
+
+    // Read replays, saves and levels before opening a window, in case they're broken
+    let simulation = if let Some(path) = replay {
+        match Replay::load(&path) {
+            // A replay always plays on its own level
+            Ok(replay) => match world::Simulation::from_replay(replay) {
+                Ok(simulation) => Some(simulation),
+                Err(e) => {
+                    println!("Couldn't load the level of replay {}: {}", path, e);
+                    return;
+                }
+            },
+            Err(e) => {
+                println!("Couldn't read replay {}: {}", path, e);
+                return;
+            }
+        }
+    } else if let Some(path) = load {
+        match world::Simulation::load(&path) {
+            Ok(simulation) => Some(simulation),
+            Err(e) => {
+                println!("Couldn't load saved game {}: {}", path, e);
+                return;
+            }
+        }
+    } else if let Some(level) = level {
+        match world::Simulation::with_options(&level, rand::random(), strict) {
+            Ok(simulation) => Some(simulation),
+            // Start in the main menu instead, so a typo doesn't stop the game from starting
+            Err(e) => {
+                println!("Couldn't load level {}: {}", level, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     // ?
     let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
         let mut path = path::PathBuf::from(manifest_dir);
@@ -31,7 +85,8 @@ fn main() {
     // build context and event_loop
     let (ctx, event_loop) = &mut cb.build().unwrap();
 
-    let state = &mut MainState::new(ctx, &resource_dir);
+    let state = &mut MainState::new(ctx, simulation, record.as_deref());
+
     match event::run(ctx, event_loop, state) {
         Ok(_) => println!("Exited cleanly."),
         Err(e) => println!("Error occured: {}", e)
@@ -40,24 +95,40 @@ fn main() {
 
 // This struct contains all the state relevant to our game.
 struct MainState {
-    world: world::World,
+    scenes: scenes::Stack,
     input_binding: input::Binding,
 }
 
 impl MainState {
-    fn new(ctx: &mut Context, _resource_path: &path::Path) -> Self {
-        // let world = world::World::new(resource_path);
-        // let mut scenestack = scenes::Stack::new(ctx, world);
-        // let initial_scene = Box::new(scenes::level::LevelScene::new(ctx, &mut scenestack.world));
-        // scenestack.push(initial_scene);
-
-        // Self {
-        //     input_binding: input::create_input_binding(),
-        //     scenes: scenestack,
-        // }
+    // Start in the main menu, or play the given simulation on top of it if there is one.
+    fn new(ctx: &mut Context, simulation: Option<world::Simulation>, record: Option<&str>) -> Self {
+        let mut scenes = scenes::Stack::new();
+        scenes.push(Box::new(MainMenu::new(ctx)));
+
+        if let Some(simulation) = simulation {
+            let mut level = LevelScene::new(ctx, simulation);
+            if let Some(path) = record {
+                level = level.record(path);
+            }
+            scenes.push(Box::new(level));
+        }
+
         Self {
-            world: world::World::new(ctx),
-            input_binding: input::create_input_binding(),
+            scenes,
+            input_binding: load_input_binding(ctx),
+        }
+    }
+}
+// Read the player's controls, or fall back to the default ones if the file is broken
+fn load_input_binding(ctx: &Context) -> input::Binding {
+    let path = filesystem::user_config_dir(ctx).join(BINDINGS_FILE);
+
+    match input::load_bindings(&path) {
+        Ok(binding) => binding,
+        Err(e) => {
+            println!("Couldn't load controls from {}: {}", path.display(), e);
+            println!("Using the default controls");
+            input::create_input_binding()
         }
     }
 }
@@ -65,25 +136,22 @@ impl MainState {
 impl event::EventHandler for MainState {
     // This function is run as fast as possible on our system.
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        // This code should be under mouse event
-        // {
-        //     let pos = ggez::input::mouse::position(&ctx);
-        //     let mut mouse_input = &mut *self.scenes.world.specs_world.fetch_mut::<input::MouseInput>();
-        //     mouse_input.pos = pos.into();
-        // }
-
-        const DESIRED_FPS: u32 = 60;
-        while timer::check_update_time(ctx, DESIRED_FPS) {
-            self.world.update(ctx);
+        // Scenes step at a fixed rate, however fast we're drawing
+        while timer::check_update_time(ctx, TICKS_PER_SECOND) {
+            self.scenes.update(ctx);
+        }
+
+        // The last scene was left
+        if self.scenes.is_empty() {
+            event::quit(ctx);
         }
-        // self.scenes.world.resources.sync(ctx);
 
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::clear(ctx, graphics::Color::from((0.0, 0.0, 0.4, 0.0)));
-        self.world.draw(ctx)?;
+        self.scenes.draw(ctx)?;
         graphics::present(ctx)
     }
 
@@ -91,11 +159,12 @@ impl event::EventHandler for MainState {
         &mut self,
         _ctx: &mut Context,
         keycode: event::KeyCode,
-        _keymod: event::KeyMods,
+        keymod: event::KeyMods,
         _repeat: bool,
     ) {
+        self.scenes.modifiers(keymod);
         if let Some(ev) = self.input_binding.resolve(keycode) {
-            self.world.handle_input(ev, true);
+            self.scenes.input(ev, true);
         }
     }
 
@@ -103,34 +172,39 @@ impl event::EventHandler for MainState {
         &mut self,
         _ctx: &mut Context,
         keycode: event::KeyCode,
-        _keymod: event::KeyMods,
+        keymod: event::KeyMods,
     ) {
+        self.scenes.modifiers(keymod);
         if let Some(ev) = self.input_binding.resolve(keycode) {
-            self.world.handle_input(ev, false);
+            self.scenes.input(ev, false);
         }
     }
 
     fn mouse_button_down_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         button: event::MouseButton,
         _x: f32,
         _y: f32
     ) {
+        // Mouse events don't come with the modifiers, so ask which are held
+        self.scenes.modifiers(ggez::input::keyboard::active_mods(ctx));
         if let Some(ev) = self.input_binding.resolve(button) {
-            self.world.handle_input(ev, true);
+            self.scenes.input(ev, true);
         }
     }
 
     fn mouse_button_up_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         button: event::MouseButton,
         _x: f32,
         _y: f32
     ) {
+        // Mouse events don't come with the modifiers, so ask which are held
+        self.scenes.modifiers(ggez::input::keyboard::active_mods(ctx));
         if let Some(ev) = self.input_binding.resolve(button) {
-            self.world.handle_input(ev, false);
+            self.scenes.input(ev, false);
         }
     }
 
@@ -142,6 +216,24 @@ impl event::EventHandler for MainState {
         _dx: f32,
         _dy: f32
     ) {
-        self.world.handle_mouse_motion(x, y);
+        self.scenes.mouse_motion(x, y);
     }
-}
\ No newline at end of file
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        match self.input_binding.resolve(input::InputType::MouseWheel) {
+            Some(input::Event::Axis(axis, positive)) => {
+                self.scenes.axis_impulse(axis, if positive { y } else { -y });
+            }
+            // The wheel can't be held down, so bound to a button it's a quick click
+            Some(ev) => {
+                self.scenes.input(ev, true);
+                self.scenes.input(ev, false);
+            }
+            None => (),
+        }
+    }
+
+    fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) {
+        self.scenes.resize(width, height);
+    }
+}
@@ -0,0 +1,197 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use ggez_goodies::Point2;
+
+use crate::level::TILE_SIZE;
+
+// Cost of moving to a neighbouring cell. Diagonals are roughly sqrt(2) times as expensive.
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+// A grid of level tiles telling which ones can be walked through, and how fast.
+// Cell (x, y) is centered on the world position (x * TILE_SIZE, y * TILE_SIZE),
+// the same place the level loader puts the wall tile.
+#[derive(Clone, Debug, Default)]
+pub struct NavGrid {
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+    // Walking speed multiplier for each cell, 1.0 is normal speed
+    speed: Vec<f32>,
+}
+
+impl NavGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        NavGrid {
+            width,
+            height,
+            blocked: vec![false; width * height],
+            speed: vec![1.0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn set_blocked(&mut self, x: usize, y: usize, blocked: bool) {
+        let index = self.index(x, y);
+        self.blocked[index] = blocked;
+    }
+
+    // Make walking through the cell slower (or faster). Speed has to be above zero,
+    // use set_blocked for cells that can't be walked through at all.
+    pub fn set_speed(&mut self, x: usize, y: usize, speed: f32) {
+        let index = self.index(x, y);
+        self.speed[index] = speed;
+    }
+
+    pub fn speed(&self, x: usize, y: usize) -> f32 {
+        self.speed[self.index(x, y)]
+    }
+
+    // The walking speed multiplier at a world position. Outside of the grid is normal speed.
+    pub fn speed_at(&self, pos: Point2) -> f32 {
+        match self.cell_at(pos) {
+            Some((x, y)) => self.speed[self.index(x, y)],
+            None => 1.0,
+        }
+    }
+
+    // Cells outside of the grid count as blocked.
+    pub fn is_blocked(&self, x: isize, y: isize) -> bool {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return true;
+        }
+
+        self.blocked[self.index(x as usize, y as usize)]
+    }
+
+    // The cell containing a world position, if it is inside the grid.
+    pub fn cell_at(&self, pos: Point2) -> Option<(usize, usize)> {
+        let x = ((pos.x + TILE_SIZE / 2.0) / TILE_SIZE).floor();
+        let y = ((pos.y + TILE_SIZE / 2.0) / TILE_SIZE).floor();
+
+        if x < 0.0 || y < 0.0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+
+        Some((x as usize, y as usize))
+    }
+
+    pub fn cell_center(x: usize, y: usize) -> Point2 {
+        Point2::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE)
+    }
+
+    // Find a list of waypoints leading from start to goal, going around blocked cells.
+    // The last waypoint is always the goal itself. Returns None if either end is outside
+    // the grid or inside a wall, or if there is no way through.
+    pub fn find_path(&self, start: Point2, goal: Point2) -> Option<Vec<Point2>> {
+        let start_cell = self.cell_at(start)?;
+        let goal_cell = self.cell_at(goal)?;
+
+        if self.blocked[self.index(goal_cell.0, goal_cell.1)] {
+            return None;
+        }
+
+        let cells = self.find_cell_path(start_cell, goal_cell)?;
+
+        // Only keep the cells where the path changes direction, the rest are on a straight line anyway.
+        // The start cell is dropped since we're already in it, and the goal cell is replaced by the goal.
+        let mut waypoints = Vec::new();
+        for i in 1..cells.len().saturating_sub(1) {
+            let (px, py) = cells[i - 1];
+            let (x, y) = cells[i];
+            let (nx, ny) = cells[i + 1];
+
+            let direction_in = (x as isize - px as isize, y as isize - py as isize);
+            let direction_out = (nx as isize - x as isize, ny as isize - y as isize);
+
+            if direction_in != direction_out {
+                waypoints.push(Self::cell_center(x, y));
+            }
+        }
+        waypoints.push(goal);
+
+        Some(waypoints)
+    }
+
+    // A* over the grid cells. Returns every cell on the path, start and goal included.
+    fn find_cell_path(&self, start: (usize, usize), goal: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        let start_index = self.index(start.0, start.1);
+        let goal_index = self.index(goal.0, goal.1);
+
+        let mut cost = vec![u32::MAX; self.blocked.len()];
+        let mut came_from: Vec<Option<usize>> = vec![None; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+
+        cost[start_index] = 0;
+        open.push(Reverse((self.heuristic(start, goal), start_index)));
+
+        while let Some(Reverse((_, index))) = open.pop() {
+            if index == goal_index {
+                // Walk backwards from the goal to build the path.
+                let mut path = vec![goal];
+                let mut current = index;
+                while let Some(previous) = came_from[current] {
+                    path.push((previous % self.width, previous / self.width));
+                    current = previous;
+                }
+                path.reverse();
+
+                return Some(path);
+            }
+
+            let (x, y) = (index % self.width, index / self.width);
+
+            for (dx, dy) in &[(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if self.is_blocked(nx, ny) {
+                    continue;
+                }
+
+                let step_cost = if *dx != 0 && *dy != 0 {
+                    // Don't cut corners, the unit would get stuck on the wall.
+                    if self.is_blocked(nx, y as isize) || self.is_blocked(x as isize, ny) {
+                        continue;
+                    }
+                    DIAGONAL_COST
+                } else {
+                    STRAIGHT_COST
+                };
+
+                let neighbour = (nx as usize, ny as usize);
+                let neighbour_index = self.index(neighbour.0, neighbour.1);
+
+                // Slow cells take longer to walk through
+                let step_cost = (step_cost as f32 / self.speed[neighbour_index]).round() as u32;
+                let new_cost = cost[index] + step_cost;
+
+                if new_cost < cost[neighbour_index] {
+                    cost[neighbour_index] = new_cost;
+                    came_from[neighbour_index] = Some(index);
+                    open.push(Reverse((new_cost + self.heuristic(neighbour, goal), neighbour_index)));
+                }
+            }
+        }
+
+        None
+    }
+
+    // Octile distance, the exact cost between two cells if there were no walls or slow terrain.
+    fn heuristic(&self, a: (usize, usize), b: (usize, usize)) -> u32 {
+        let dx = (a.0 as isize - b.0 as isize).unsigned_abs() as u32;
+        let dy = (a.1 as isize - b.1 as isize).unsigned_abs() as u32;
+
+        STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+}
@@ -1,14 +1,161 @@
-use specs::{self, World, Entity};
+use std::collections::HashMap;
+use std::{error, fmt};
+
+use specs::{self, World, WorldExt, Entity, ReaderId, storage::ComponentEvent};
 use collider::{*, geom::{Vec2, v2}};
 
-use crate::components::BoxCollider;
+use crate::components::{BoxCollider, ColliderShape, Position};
+use crate::resources::{CollisionEvent, Contact};
 
 pub type ColliderRes = Collider<Profile>;
 
-#[derive(Copy, Clone, Debug)]
+// Sets of collision layers. Every hitbox is on one layer, and has a mask of the layers it collides with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Layers(u8);
+
+impl Layers {
+    pub const NONE: Layers = Layers(0);
+    // Level walls
+    pub const WALL: Layers = Layers(1);
+    // Units walking on the ground
+    pub const GROUND: Layers = Layers(1 << 1);
+    // Things that are shot
+    pub const PROJECTILE: Layers = Layers(1 << 2);
+    // Areas that notice what enters them, without pushing anything
+    pub const TRIGGER: Layers = Layers(1 << 3);
+    // Flying units, they go over walls
+    pub const AIR: Layers = Layers(1 << 4);
+    // Level water, it only stops units on the ground. Things are shot and seen across it.
+    pub const WATER: Layers = Layers(1 << 5);
+    pub const ALL: Layers = Layers(0b111111);
+
+    // Names of the single layers, as they are written in files
+    const NAMES: [(Layers, &'static str); 6] = [
+        (Layers::WALL, "Wall"),
+        (Layers::GROUND, "Ground"),
+        (Layers::PROJECTILE, "Projectile"),
+        (Layers::TRIGGER, "Trigger"),
+        (Layers::AIR, "Air"),
+        (Layers::WATER, "Water"),
+    ];
+
+    pub fn contains(self, other: Layers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(self, other: Layers) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    // What a hitbox on this layer collides with, unless it is told otherwise
+    pub fn default_mask(self) -> Layers {
+        let mut mask = Layers::NONE;
+        if self.intersects(Layers::WALL) {
+            mask = mask | Layers::GROUND | Layers::PROJECTILE;
+        }
+        if self.intersects(Layers::GROUND) {
+            mask = mask | Layers::WALL | Layers::GROUND | Layers::PROJECTILE | Layers::TRIGGER | Layers::WATER;
+        }
+        if self.intersects(Layers::PROJECTILE) {
+            mask = mask | Layers::WALL | Layers::GROUND | Layers::AIR;
+        }
+        if self.intersects(Layers::TRIGGER) {
+            mask = mask | Layers::GROUND | Layers::AIR;
+        }
+        if self.intersects(Layers::AIR) {
+            mask = mask | Layers::AIR | Layers::PROJECTILE | Layers::TRIGGER;
+        }
+        if self.intersects(Layers::WATER) {
+            mask = mask | Layers::GROUND;
+        }
+        mask
+    }
+
+    // Like "Wall+Ground", or "None" for no layers at all
+    pub fn name(&self) -> String {
+        let names: Vec<&str> = Self::NAMES.iter()
+            .filter(|(layer, _)| self.contains(*layer))
+            .map(|(_, name)| *name)
+            .collect();
+
+        match names.is_empty() {
+            true => "None".to_string(),
+            false => names.join("+"),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Layers> {
+        if name == "None" {
+            return Some(Layers::NONE);
+        }
+
+        name.split('+').try_fold(Layers::NONE, |layers, name| {
+            let (layer, _) = Self::NAMES.iter().find(|(_, known)| *known == name)?;
+            Some(layers | *layer)
+        })
+    }
+}
+
+impl std::ops::BitOr for Layers {
+    type Output = Layers;
+
+    fn bitor(self, other: Layers) -> Layers {
+        Layers(self.0 | other.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Profile {
     pub id: specs::world::Index,
-    pub wall: bool,
+    // The layer the hitbox is on
+    pub layer: Layers,
+    // The layers it collides with
+    pub mask: Layers,
+    // The entity that made this one, like the unit that fired a projectile. They never collide.
+    pub owner: Option<specs::world::Index>,
+}
+
+impl Profile {
+    // A hitbox for the entity on the layer, colliding with what that layer does by default.
+    pub fn new(entity: Entity, layer: Layers) -> Self {
+        Profile {
+            id: entity.id(),
+            layer,
+            mask: layer.default_mask(),
+            owner: None,
+        }
+    }
+
+    pub fn with_mask(mut self, mask: Layers) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn with_owner(mut self, owner: Entity) -> Self {
+        self.owner = Some(owner.id());
+        self
+    }
+
+    // For finding the hitboxes on the given layers with Collider::query_overlaps.
+    // Its id isn't any entity's, so nothing is left out for being the querying entity or its child.
+    pub fn query(mask: Layers) -> Self {
+        Profile {
+            id: specs::world::Index::MAX,
+            layer: Layers::ALL,
+            mask,
+            owner: None,
+        }
+    }
+
+    // Walls and water never move, whatever bumps into them is pushed out
+    pub fn is_wall(&self) -> bool {
+        self.layer.intersects(Layers::WALL | Layers::WATER)
+    }
+
+    // Solid hitboxes push each other apart, triggers only notice each other
+    pub fn is_solid(&self) -> bool {
+        !self.layer.intersects(Layers::TRIGGER)
+    }
 }
 
 impl HbProfile for Profile {
@@ -16,29 +163,227 @@ impl HbProfile for Profile {
         self.id as HbId
     }
 
-    fn can_interact(&self, _other: &Profile) -> bool { true }
+    // Both hitboxes have to want to collide with each other
+    fn can_interact(&self, other: &Profile) -> bool {
+        if self.owner == Some(other.id) || other.owner == Some(self.id) {
+            return false;
+        }
+
+        self.mask.intersects(other.layer) && other.mask.intersects(self.layer)
+    }
+
     fn cell_width() -> f64 { 35.0 }
     fn padding() -> f64 { 0.01 }
 }
 
-pub fn add_box_collider(entity: Entity, hitbox: Hitbox, wall: bool, world: &mut World) {
-    let mut collider = world.fetch_mut::<Option<ColliderRes>>();
-    let collider = collider.as_mut().unwrap();
+// The collider resource is added when the dispatcher is set up, hitboxes can't be added before that.
+#[derive(Debug)]
+pub struct NoColliderError;
+
+impl fmt::Display for NoColliderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the collider hasn't been set up yet")
+    }
+}
+
+impl error::Error for NoColliderError {}
+
+// Why an entity couldn't be given a hitbox
+#[derive(Debug)]
+pub enum HitboxError {
+    NoCollider,
+    // The entity has nothing saying how big its hitbox is
+    NoShape(Entity),
+    // The entity has nowhere to put its hitbox
+    NoPosition(Entity),
+}
+
+impl fmt::Display for HitboxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HitboxError::NoCollider => write!(f, "{}", NoColliderError),
+            HitboxError::NoShape(entity) => write!(f, "entity {} has no ColliderShape", entity.id()),
+            HitboxError::NoPosition(entity) => write!(f, "entity {} has no Position", entity.id()),
+        }
+    }
+}
+
+impl error::Error for HitboxError {}
+
+impl From<NoColliderError> for HitboxError {
+    fn from(_: NoColliderError) -> Self {
+        HitboxError::NoCollider
+    }
+}
+
+// Keeps track of which BoxColliders were removed, so their hitboxes can be taken out of the collider.
+// Hitboxes are keyed by entity index, and specs hands an index out again once its entity is deleted,
+// so the old hitbox has to be gone before a new entity with the same index gets one.
+// The collider panics when asked to remove a hitbox it doesn't have, so the hitboxes that were added
+// are kept too, with the entity they belong to. A BoxCollider inserted without a hitbox, or a removal
+// that was already handled, is skipped.
+pub struct HitboxRemovals {
+    reader: ReaderId<ComponentEvent>,
+    live: HashMap<HbId, Entity>,
+    // A removed hitbox stops touching everything it touched. The collision system reports it.
+    exits: Vec<CollisionEvent>,
+}
+
+impl HitboxRemovals {
+    // The exits of the hitboxes removed since the last time
+    pub fn take_exits(&mut self) -> Vec<CollisionEvent> {
+        std::mem::take(&mut self.exits)
+    }
+}
+
+// Add an empty collider to the world and start tracking removed BoxColliders.
+pub fn setup_collider(world: &mut World) {
+    let collider: ColliderRes = Collider::new();
+    world.insert(Some(collider));
+
+    let reader = world.write_storage::<BoxCollider>().register_reader();
+    world.insert(HitboxRemovals { reader, live: HashMap::new(), exits: Vec::new() });
+}
+
+// Take the hitboxes of deleted entities, and of entities whose BoxCollider was removed, out of the collider.
+pub fn remove_stale_hitboxes(collider: &mut ColliderRes, box_collider: &specs::ReadStorage<BoxCollider>, removals: &mut HitboxRemovals) {
+    for event in box_collider.channel().read(&mut removals.reader) {
+        if let ComponentEvent::Removed(index) = event {
+            let entity = match removals.live.remove(&(*index as HbId)) {
+                Some(entity) => entity,
+                None => continue,
+            };
+
+            for other in collider.remove_hitbox(*index as HbId) {
+                if let Some(&other) = removals.live.get(&other.id()) {
+                    let (first, second) = if entity.id() <= other.id() { (entity, other) } else { (other, entity) };
+                    removals.exits.push(CollisionEvent { contact: Contact::Exit, first, second });
+                }
+            }
+        }
+    }
+}
+
+// Like remove_stale_hitboxes, for use outside of systems.
+pub fn sync_collider(world: &World) -> Result<(), NoColliderError> {
+    let mut collider = world.try_fetch_mut::<Option<ColliderRes>>().ok_or(NoColliderError)?;
+    let collider = collider.as_mut().ok_or(NoColliderError)?;
+    let mut removals = world.try_fetch_mut::<HitboxRemovals>().ok_or(NoColliderError)?;
+
+    remove_stale_hitboxes(collider, &world.read_storage(), &mut removals);
 
-    let profile = Profile {
-        id:  entity.id(),
-        wall,
+    Ok(())
+}
+
+// Give the entity a hitbox on the layer, colliding with what that layer does by default.
+// The hitbox is shaped like the entity's ColliderShape and put at its Position, it fails without either.
+pub fn add_box_collider(entity: Entity, layer: Layers, world: &mut World) -> Result<(), HitboxError> {
+    add_collider(entity, Profile::new(entity, layer), world)
+}
+
+// Give the entity a hitbox with the profile, for when the layer's defaults don't fit.
+pub fn add_collider(entity: Entity, profile: Profile, world: &mut World) -> Result<(), HitboxError> {
+    let hitbox = {
+        let shape = world.read_storage::<ColliderShape>();
+        let pos = world.read_storage::<Position>();
+        let shape = shape.get(entity).ok_or(HitboxError::NoShape(entity))?;
+        shape.place(pos.get(entity).ok_or(HitboxError::NoPosition(entity))?.0).still()
     };
 
+    Ok(add_hitbox(entity, hitbox, profile, world)?)
+}
+
+// Give the entity a hitbox that was already placed, like one read back from a saved game.
+// A hitbox the entity already had is replaced.
+pub fn add_hitbox(entity: Entity, hitbox: Hitbox, profile: Profile, world: &mut World) -> Result<(), NoColliderError> {
+    world.write_storage::<BoxCollider>().remove(entity);
+    sync_collider(world)?;
+
+    let mut collider = world.try_fetch_mut::<Option<ColliderRes>>().ok_or(NoColliderError)?;
+    let collider = collider.as_mut().ok_or(NoColliderError)?;
+    let mut removals = world.try_fetch_mut::<HitboxRemovals>().ok_or(NoColliderError)?;
+
     collider.add_hitbox(profile, hitbox);
+    removals.live.insert(profile.id(), entity);
 
     let mut box_collider: specs::WriteStorage<BoxCollider> = world.system_data();
-    box_collider.insert(entity, BoxCollider(entity.id() as HbId)).unwrap();
+    box_collider.insert(entity, BoxCollider(profile)).unwrap();
+
+    Ok(())
 }
 
 const SMALL: f64 = 0.01;
 
-pub fn handle_wall_collision(entity: Profile, wall: Profile, collider: &mut ColliderRes) -> Vec<Profile> {
+// Velocities slower than this are rounding errors left over from resolving collisions.
+// The collider can't handle speeds this close to zero, so they are snapped to zero.
+const MIN_SPEED: f64 = 1e-6;
+
+fn snap_velocity(vel: Vec2) -> Vec2 {
+    if vel.len() < MIN_SPEED {
+        Vec2::zero()
+    } else {
+        vel
+    }
+}
+
+// Overlaps that started or ended, with the lowest id first
+pub type Contacts = Vec<(Contact, Profile, Profile)>;
+
+fn ordered(a: Profile, b: Profile) -> (Profile, Profile) {
+    if a.id <= b.id { (a, b) } else { (b, a) }
+}
+
+// Move hitboxes by taking them out of the collider and putting them back in. The collider
+// doesn't report the overlaps that start or end this way, so they are added to the contacts.
+pub fn replace_hitboxes(collider: &mut ColliderRes, hitboxes: Vec<(Profile, Hitbox)>, contacts: &mut Contacts) {
+    let mut before: Vec<(Profile, Profile)> = Vec::new();
+    for (profile, _) in &hitboxes {
+        before.extend(collider.remove_hitbox(profile.id()).into_iter().map(|other| ordered(*profile, other)));
+    }
+
+    let mut after: Vec<(Profile, Profile)> = Vec::new();
+    for (profile, hitbox) in hitboxes {
+        after.extend(collider.add_hitbox(profile, hitbox).into_iter().map(|other| ordered(profile, other)));
+    }
+
+    // The collider lists overlaps in no particular order
+    let by_ids = |(a, b): &(Profile, Profile)| (a.id, b.id);
+    before.sort_by_key(by_ids);
+    after.sort_by_key(by_ids);
+
+    for &(a, b) in before.iter().filter(|pair| !after.contains(pair)) {
+        contacts.push((Contact::Exit, a, b));
+    }
+    for &(a, b) in after.iter().filter(|pair| !before.contains(pair)) {
+        contacts.push((Contact::Enter, a, b));
+    }
+}
+
+// Boil the contacts of a whole tick down to the overlaps that are different at the end of it than
+// at the start. Units pushing each other touch and are pushed apart again many times in a tick,
+// and none of that is worth telling anyone about. Every contact is a change, so a pair was
+// overlapping at the start if the first thing that happened to it was an exit.
+// What is left is in the order each pair last changed.
+pub fn changed_contacts(contacts: Contacts) -> Contacts {
+    // For every pair, its first contact and the index of its last one
+    let mut pairs: HashMap<(u32, u32), (Contact, usize)> = HashMap::new();
+    for (i, (contact, a, b)) in contacts.iter().enumerate() {
+        pairs.entry((a.id, b.id)).or_insert((*contact, i)).1 = i;
+    }
+
+    contacts.iter()
+        .enumerate()
+        .filter(|(i, (contact, a, b))| {
+            let (first, last) = pairs[&(a.id, b.id)];
+            let overlapping_before = first == Contact::Exit;
+            let overlapping_after = *contact == Contact::Enter;
+            last == *i && overlapping_before != overlapping_after
+        })
+        .map(|(_, contact)| *contact)
+        .collect()
+}
+
+pub fn handle_wall_collision(entity: Profile, wall: Profile, collider: &mut ColliderRes, contacts: &mut Contacts) -> Vec<Profile> {
     // Get hitboxes
     let mut entity_hb = collider.get_hitbox(entity.id());
     let wall_hb = collider.get_hitbox(wall.id());
@@ -47,8 +392,7 @@ pub fn handle_wall_collision(entity: Profile, wall: Profile, collider: &mut Coll
     entity_hb = resolve_wall_collision(entity_hb, wall_hb);
 
     // Apply new hitbox
-    let _ = collider.remove_hitbox(entity.id());
-    collider.add_hitbox(entity, entity_hb);
+    replace_hitboxes(collider, vec![(entity, entity_hb)], contacts);
 
     // Get new collisions
     collider.get_overlaps(entity.id())
@@ -65,26 +409,57 @@ pub fn resolve_wall_collision(entity_hb: Hitbox, wall_hb: Hitbox) -> Hitbox {
     let new_vec = {
         let b = v2(normal.dir().y, -normal.dir().x);
 
-        dot_product(entity_hb.vel.value, b) * b
+        snap_velocity(dot_product(entity_hb.vel.value, b) * b)
     };
 
     // Return new hitbox with updated data
-    return entity_hb.value.shape.place(new_pos).moving(new_vec);
+    entity_hb.value.shape.place(new_pos).moving(new_vec)
 }
 
-pub fn resolve_entity_collision(hb_1: Hitbox, hb_2: Hitbox) -> (Hitbox, Hitbox) {
-    // Get collision normal
+// How easily something is pushed in a collision, the inverse of its mass. Zero for things that never budge.
+pub fn push_weight(mass: f32, holding: bool) -> f64 {
+    if holding || mass <= 0.0 {
+        0.0
+    } else {
+        1.0 / mass as f64
+    }
+}
+
+// Push two entities apart. The push weights decide which of them gives way: one with twice the weight
+// is pushed twice as far, and one with a weight of zero isn't pushed at all. When they are running
+// into each other they carry on together at the speed their masses average out to, so a moving unit
+// shoves a lighter or equal one that's standing still along.
+pub fn resolve_entity_collision(hb_1: Hitbox, hb_2: Hitbox, weight_1: f64, weight_2: f64) -> (Hitbox, Hitbox) {
+    // Two things that both never budge push each other equally, so they don't stay stuck together
+    let (weight_1, weight_2) = match weight_1 + weight_2 > 0.0 {
+        true => (weight_1, weight_2),
+        false => (1.0, 1.0),
+    };
+    let share_1 = weight_1 / (weight_1 + weight_2);
+    let share_2 = weight_2 / (weight_1 + weight_2);
+
+    // Get collision normal, pointing from the second hitbox to the first
     let normal = hb_1.value.normal_from(&hb_2.value);
+    let n = normal.dir();
 
     // Apply collision normal to entity pos, thereby placing it outside of the collider.
-    let new_pos_1 = hb_1.value.pos + normal.dir()*SMALL;
-    let new_pos_2 = hb_2.value.pos - normal.dir()*SMALL;
+    let new_pos_1 = hb_1.value.pos + n*(SMALL*2.0*share_1);
+    let new_pos_2 = hb_2.value.pos - n*(SMALL*2.0*share_2);
 
-    // Project velocity vector onto the orthogonal of the normal as to reset movement in the direction of the collider.
-    let b = v2(normal.dir().y, -normal.dir().x);
+    // Split both velocities into the part along the normal and the part along the orthogonal of it
+    let b = v2(n.y, -n.x);
+    let (along_1, along_2) = (dot_product(hb_1.vel.value, n), dot_product(hb_2.vel.value, n));
+
+    // If they're closing in on each other, the lighter one takes on more of the heavier one's speed
+    let (along_1, along_2) = if along_1 < along_2 {
+        let together = along_1 * share_2 + along_2 * share_1;
+        (together, together)
+    } else {
+        (along_1, along_2)
+    };
 
-    let new_vec_1 = dot_product(hb_1.vel.value, b) * b;
-    let new_vec_2 = dot_product(hb_2.vel.value, b) * b;
+    let new_vec_1 = snap_velocity(dot_product(hb_1.vel.value, b) * b + n * along_1);
+    let new_vec_2 = snap_velocity(dot_product(hb_2.vel.value, b) * b + n * along_2);
 
     // Return new hitbox with updated data
     (
@@ -0,0 +1,151 @@
+//! Asking the collider what is where, instead of going through every `Position`.
+//!
+//! Every query only finds hitboxes on the given layers, and returns the entities they belong to
+//! ordered by id, so the results don't depend on the order the hitboxes were added in.
+use collider::{HbProfile, geom::{Shape, ShapeKind, PlacedShape, v2}};
+use ggez::graphics::Rect;
+use ggez_goodies::{Point2, Vector2};
+use specs::{world::EntitiesRes, Entity};
+
+use crate::physics::{ColliderRes, Layers, Profile};
+
+// Points are looked up as a circle this wide
+const POINT_SIZE: f64 = 1.0;
+
+pub struct SpatialQuery<'a> {
+    collider: &'a ColliderRes,
+    entities: &'a EntitiesRes,
+}
+
+impl<'a> SpatialQuery<'a> {
+    pub fn new(collider: &'a ColliderRes, entities: &'a EntitiesRes) -> Self {
+        SpatialQuery { collider, entities }
+    }
+
+    // Everything with a hitbox under the point
+    pub fn at_point(&self, point: Point2, layers: Layers) -> Vec<Entity> {
+        self.overlapping(&Shape::circle(POINT_SIZE).place(v2(point.x as f64, point.y as f64)), layers)
+    }
+
+    // Everything with a hitbox reaching into the circle
+    pub fn in_circle(&self, center: Point2, radius: f32, layers: Layers) -> Vec<Entity> {
+        let circle = Shape::circle(radius as f64 * 2.0).place(v2(center.x as f64, center.y as f64));
+        self.overlapping(&circle, layers)
+    }
+
+    // Everything with a hitbox reaching into the rectangle, like the part of the world on screen
+    pub fn in_rect(&self, rect: Rect, layers: Layers) -> Vec<Entity> {
+        let center = v2((rect.x + rect.w / 2.0) as f64, (rect.y + rect.h / 2.0) as f64);
+        self.overlapping(&Shape::rect(v2(rect.w as f64, rect.h as f64)).place(center), layers)
+    }
+
+    // The entity that passes the filter with the middle of its hitbox closest to the point, if any is
+    // within the radius. Ties go to the lowest id.
+    pub fn nearest<F>(&self, center: Point2, radius: f32, layers: Layers, filter: F) -> Option<Entity>
+    where
+        F: Fn(Entity) -> bool,
+    {
+        self.in_circle(center, radius, layers)
+            .into_iter()
+            .filter(|&entity| filter(entity))
+            .map(|entity| (entity, (self.position(entity) - center).length()))
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity)
+    }
+
+    // The first hitbox on the way from one point to the other, and where the line hits it.
+    // A hitbox the line starts in is hit right at the start.
+    pub fn raycast(&self, from: Point2, to: Point2, layers: Layers) -> Option<(Entity, Point2)> {
+        let direction = to - from;
+
+        // Only hitboxes in the box around the line can be in the way
+        let (x1, x2) = (from.x.min(to.x), from.x.max(to.x));
+        let (y1, y2) = (from.y.min(to.y), from.y.max(to.y));
+        let area = Rect::new(x1 - 1.0, y1 - 1.0, x2 - x1 + 2.0, y2 - y1 + 2.0);
+
+        self.in_rect(area, layers)
+            .into_iter()
+            .filter_map(|entity| {
+                let shape = self.collider.get_hitbox(entity.id() as u64).value;
+                hit_time(&shape, from, direction).map(|time| (entity, time))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, time)| (entity, from + direction * time))
+    }
+
+    // Whether nothing on the wall layer is between the two points. Water can be seen across.
+    pub fn line_of_sight(&self, from: Point2, to: Point2) -> bool {
+        self.raycast(from, to, Layers::WALL).is_none()
+    }
+
+    // The middle of the entity's hitbox
+    fn position(&self, entity: Entity) -> Point2 {
+        let pos = self.collider.get_hitbox(entity.id() as u64).value.pos;
+        Point2::new(pos.x as f32, pos.y as f32)
+    }
+
+    fn overlapping(&self, shape: &PlacedShape, layers: Layers) -> Vec<Entity> {
+        let mut found: Vec<Entity> = self.collider.query_overlaps(shape, &Profile::query(layers))
+            .into_iter()
+            .map(|profile| self.entities.entity(profile.id() as u32))
+            .filter(|&entity| self.entities.is_alive(entity))
+            .collect();
+
+        found.sort_by_key(|entity| entity.id());
+        found
+    }
+}
+
+// How far along the line from start to start + direction it first touches the shape,
+// from 0 at the start to 1 at the end. None if it misses.
+fn hit_time(shape: &PlacedShape, start: Point2, direction: Vector2) -> Option<f32> {
+    let center = Point2::new(shape.pos.x as f32, shape.pos.y as f32);
+    let offset = start - center;
+
+    let time = match shape.kind() {
+        ShapeKind::Circle => {
+            let radius = shape.dims().x as f32 / 2.0;
+            if offset.length() <= radius {
+                return Some(0.0);
+            }
+
+            // Solve |offset + direction * t| = radius for the smaller t
+            let a = direction.dot(direction);
+            let b = 2.0 * offset.dot(direction);
+            let c = offset.dot(offset) - radius * radius;
+            let discriminant = b * b - 4.0 * a * c;
+            if a == 0.0 || discriminant < 0.0 {
+                return None;
+            }
+            (-b - discriminant.sqrt()) / (2.0 * a)
+        }
+        ShapeKind::Rect => {
+            // Where the line enters and leaves the slab between each pair of sides
+            let half = Vector2::new(shape.dims().x as f32 / 2.0, shape.dims().y as f32 / 2.0);
+            let mut enter = f32::NEG_INFINITY;
+            let mut leave = f32::INFINITY;
+            for (offset, direction, half) in [(offset.x, direction.x, half.x), (offset.y, direction.y, half.y)] {
+                if direction == 0.0 {
+                    if offset.abs() > half {
+                        return None;
+                    }
+                } else {
+                    let (a, b) = ((-half - offset) / direction, (half - offset) / direction);
+                    enter = enter.max(a.min(b));
+                    leave = leave.min(a.max(b));
+                }
+            }
+            if enter > leave || leave < 0.0 {
+                return None;
+            }
+            enter.max(0.0)
+        }
+    };
+
+    if (0.0..=1.0).contains(&time) {
+        Some(time)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,230 @@
+//! Recordings of everything the player did during a match, so it can be played back exactly.
+//!
+//! A replay file is plain text. The header names the level and the seed, then every line
+//! after it is one input, prefixed by the tick it arrived on:
+//!
+//! ```text
+//! ddu2020 replay 3
+//! level test.png
+//! seed 1234
+//! 0 resize 800 600
+//! 0 mouse 412.5 300
+//! 10 button Left down
+//! 12 button Left up
+//! 20 axis PanX + down
+//! 35 axis PanX + up
+//! 40 impulse Zoom -1
+//! 50 mods shift ctrl
+//! 52 mods none
+//! ```
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::{error, fmt, io};
+
+use ggez::event::KeyMods;
+
+use crate::input;
+
+// First line of every replay file. The number goes up whenever the format changes.
+const HEADER: &str = "ddu2020 replay 3";
+
+// Something that reached the simulation from outside.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ReplayEvent {
+    Input(input::Event, bool),
+    MouseMotion(f32, f32),
+    // A push to an axis, from the mouse wheel
+    AxisImpulse(input::Axis, f32),
+    Resize(f32, f32),
+    // The modifier keys held down changed
+    Modifiers(KeyMods),
+}
+
+// Names of the modifier keys in replay files, in the order they are written
+const MODIFIER_NAMES: [(KeyMods, &str); 4] = [
+    (KeyMods::SHIFT, "shift"),
+    (KeyMods::CTRL, "ctrl"),
+    (KeyMods::ALT, "alt"),
+    (KeyMods::LOGO, "logo"),
+];
+
+// Every event of a match with the tick it arrived on, and what's needed to start the same match again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub level: String,
+    pub seed: u64,
+    pub events: Vec<(u64, ReplayEvent)>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    // The file doesn't start with the header, or was made by a different version
+    BadHeader,
+    // A line couldn't be read. Lines are counted from 1.
+    BadLine(usize, String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{}", err),
+            ReplayError::BadHeader => write!(f, "not a replay file, or from a different version"),
+            ReplayError::BadLine(line, text) => write!(f, "can't read line {}: {:?}", line, text),
+        }
+    }
+}
+
+impl error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ReplayError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl Replay {
+    pub fn new(level: &str, seed: u64) -> Self {
+        Replay {
+            level: level.to_string(),
+            seed,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, tick: u64, event: ReplayEvent) {
+        self.events.push((tick, event));
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        fs::read_to_string(path)?.parse()
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "level {}", self.level)?;
+        writeln!(f, "seed {}", self.seed)?;
+
+        // Floats are written in their shortest form that reads back to exactly the same value
+        for (tick, event) in &self.events {
+            match event {
+                ReplayEvent::Input(input::Event::Button(button), started) => {
+                    let state = if *started { "down" } else { "up" };
+                    writeln!(f, "{} button {} {}", tick, button.name(), state)?
+                }
+                ReplayEvent::Input(input::Event::Axis(axis, positive), started) => {
+                    let direction = if *positive { "+" } else { "-" };
+                    let state = if *started { "down" } else { "up" };
+                    writeln!(f, "{} axis {} {} {}", tick, axis.name(), direction, state)?
+                }
+                ReplayEvent::MouseMotion(x, y) => writeln!(f, "{} mouse {} {}", tick, x, y)?,
+                ReplayEvent::AxisImpulse(axis, amount) => writeln!(f, "{} impulse {} {}", tick, axis.name(), amount)?,
+                ReplayEvent::Resize(w, h) => writeln!(f, "{} resize {} {}", tick, w, h)?,
+                ReplayEvent::Modifiers(mods) => {
+                    let names: Vec<&str> = MODIFIER_NAMES.iter()
+                        .filter(|(modifier, _)| mods.contains(*modifier))
+                        .map(|(_, name)| *name)
+                        .collect();
+                    match names.is_empty() {
+                        true => writeln!(f, "{} mods none", tick)?,
+                        false => writeln!(f, "{} mods {}", tick, names.join(" "))?,
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Replay {
+    type Err = ReplayError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text.lines();
+
+        if lines.next() != Some(HEADER) {
+            return Err(ReplayError::BadHeader);
+        }
+
+        let level = lines.next()
+            .and_then(|line| line.strip_prefix("level "))
+            .ok_or(ReplayError::BadHeader)?;
+        let seed = lines.next()
+            .and_then(|line| line.strip_prefix("seed "))
+            .and_then(|seed| seed.parse().ok())
+            .ok_or(ReplayError::BadHeader)?;
+
+        let mut replay = Replay::new(level, seed);
+
+        // The header takes up the first three lines
+        for (number, line) in lines.enumerate().map(|(i, line)| (i + 4, line)) {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (tick, event) = parse_event(line)
+                .ok_or_else(|| ReplayError::BadLine(number, line.to_string()))?;
+            replay.record(tick, event);
+        }
+
+        Ok(replay)
+    }
+}
+
+fn parse_event(line: &str) -> Option<(u64, ReplayEvent)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let tick = words.first()?.parse().ok()?;
+    let float = |i: usize| words.get(i).and_then(|word| word.parse::<f32>().ok());
+
+    let started = |state: &str| match state {
+        "down" => Some(true),
+        "up" => Some(false),
+        _ => None,
+    };
+
+    let event = match &words[1..] {
+        ["button", button, state] => {
+            let button = input::Button::from_name(button)?;
+            ReplayEvent::Input(input::Event::Button(button), started(state)?)
+        }
+        ["axis", axis, direction, state] => {
+            let axis = input::Axis::from_name(axis)?;
+            let positive = match *direction {
+                "+" => true,
+                "-" => false,
+                _ => return None,
+            };
+            ReplayEvent::Input(input::Event::Axis(axis, positive), started(state)?)
+        }
+        ["mouse", _, _] => ReplayEvent::MouseMotion(float(2)?, float(3)?),
+        ["impulse", axis, _] => ReplayEvent::AxisImpulse(input::Axis::from_name(axis)?, float(3)?),
+        ["resize", _, _] => ReplayEvent::Resize(float(2)?, float(3)?),
+        ["mods", "none"] => ReplayEvent::Modifiers(KeyMods::NONE),
+        ["mods", names @ ..] if !names.is_empty() => {
+            let mut mods = KeyMods::NONE;
+            for name in names {
+                let (modifier, _) = MODIFIER_NAMES.iter().find(|(_, known)| known == name)?;
+                mods |= *modifier;
+            }
+            ReplayEvent::Modifiers(mods)
+        }
+        _ => return None,
+    };
+
+    Some((tick, event))
+}
@@ -1,6 +1,9 @@
-use specs::World;
-use ggez_goodies::Point2;
+use ggez::graphics::Rect;
+use ggez_goodies::{Point2, Vector2};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use specs::Entity;
 
+#[derive(Default)]
 pub struct SelectionBox {
     pub start: Point2,
     pub stop: Point2
@@ -15,6 +18,228 @@ impl SelectionBox {
     }
 }
 
+// How far the camera can zoom out and in
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 4.0;
+
+// The part of the world that is shown on screen.
+// Screen positions are in pixels from the top left corner of the window.
+pub struct Camera {
+    // World position shown in the top left corner of the screen
+    pub offset: Point2,
+    // How many pixels one world unit takes up on screen
+    pub zoom: f32,
+    // Size of the window in pixels
+    pub screen_size: Vector2,
+    // Scroll when the mouse is at the edge of the window. Off without a window.
+    pub edge_scroll: bool,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            offset: Point2::zero(),
+            zoom: 1.0,
+            screen_size: Vector2::new(800.0, 600.0),
+            edge_scroll: false,
+        }
+    }
+}
+
+impl Camera {
+    pub fn screen_to_world(&self, pos: Point2) -> Point2 {
+        self.offset + pos.to_vector() / self.zoom
+    }
+
+    pub fn world_to_screen(&self, pos: Point2) -> Point2 {
+        ((pos - self.offset) * self.zoom).to_point()
+    }
+
+    // Move the camera by a distance in screen pixels.
+    pub fn pan(&mut self, screen_distance: Vector2) {
+        self.offset += screen_distance / self.zoom;
+    }
+
+    // Zoom by a factor, keeping the world position under the given screen position in place.
+    pub fn zoom_at(&mut self, screen_pos: Point2, factor: f32) {
+        let anchor = self.screen_to_world(screen_pos);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = anchor - screen_pos.to_vector() / self.zoom;
+    }
+
+    // The world position in the middle of the screen
+    pub fn center(&self) -> Point2 {
+        self.offset + self.screen_size / (2.0 * self.zoom)
+    }
+
+    pub fn center_on(&mut self, pos: Point2) {
+        self.offset = pos - self.screen_size / (2.0 * self.zoom);
+    }
+
+    // The area of the world that is visible
+    pub fn view(&self) -> Rect {
+        Rect::new(
+            self.offset.x,
+            self.offset.y,
+            self.screen_size.x / self.zoom,
+            self.screen_size.y / self.zoom,
+        )
+    }
+}
+
+// How many control groups there are, one for every number key from 1 to 9
+pub const CONTROL_GROUPS: usize = 9;
+
+// Units the player stored to select again later with a number key.
+// A unit can be in more than one group.
+#[derive(Clone, Debug, Default)]
+pub struct ControlGroups {
+    pub groups: [Vec<Entity>; CONTROL_GROUPS],
+    // The group recalled last and the tick it happened on, to notice double taps
+    pub last_recall: Option<(usize, u64)>,
+}
+
+// Whether two hitboxes started or stopped overlapping
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Contact {
+    Enter,
+    Exit,
+}
+
+// Two entities that started or stopped touching. The one with the lowest id comes first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CollisionEvent {
+    pub contact: Contact,
+    pub first: Entity,
+    pub second: Entity,
+}
+
+// Everything that is touching at the end of the last tick but wasn't at the start, or the other way
+// around. The collision system fills this at the end of every tick, so other systems see it the tick after.
+// Units pushing against each other are kept a hair apart, so they don't count as touching.
+// Entities that are despawned or lose their hitbox get an exit for whatever they were touching.
+// A loaded game starts with no events.
+#[derive(Clone, Debug, Default)]
+pub struct CollisionEvents {
+    pub events: Vec<CollisionEvent>,
+}
+
+impl CollisionEvents {
+    pub fn iter(&self) -> impl Iterator<Item = &CollisionEvent> {
+        self.events.iter()
+    }
+
+    // What the entity started or stopped touching, and which it was
+    pub fn involving(&self, entity: Entity) -> impl Iterator<Item = (Contact, Entity)> + '_ {
+        self.events.iter().filter_map(move |event| {
+            if event.first == entity {
+                Some((event.contact, event.second))
+            } else if event.second == entity {
+                Some((event.contact, event.first))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+// How many times the simulation is stepped per second
+pub const TICKS_PER_SECOND: u32 = 60;
+
+// The simulation clock. The simulation always moves forward in whole ticks of the same length,
+// however fast it is being drawn, so every run with the same input ends up the same.
+pub struct Clock {
+    // Ticks run so far
+    pub tick: u64,
+    // Length of one tick in seconds
+    pub dt: f64,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock {
+            tick: 0,
+            dt: 1.0 / TICKS_PER_SECOND as f64,
+        }
+    }
+}
+
+impl Clock {
+    // Seconds of simulation time passed so far
+    pub fn time(&self) -> f64 {
+        self.tick as f64 * self.dt
+    }
+}
+
+// The only source of randomness the simulation is allowed to use.
+// Systems must take their random numbers from here instead of thread_rng,
+// so a simulation started from the same seed always plays out the same.
+pub struct Random {
+    seed: u64,
+    rng: StdRng,
+    // How many 32 bit words have been drawn since the start
+    draws: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            draws: 0,
+        }
+    }
+
+    // The seed the simulation was started with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // The generator's state can't be written to a save file, but the seed and this count can,
+    // so a loaded game continues with the same numbers as the game it was saved from.
+    pub fn draws(&self) -> u64 {
+        self.draws
+    }
+
+    // Start over from the seed and skip the given number of draws, see draws
+    pub fn restore(&mut self, draws: u64) {
+        self.rng = StdRng::seed_from_u64(self.seed);
+        for _ in 0..draws {
+            self.rng.next_u32();
+        }
+        self.draws = draws;
+    }
+}
+
+// Every way of drawing numbers is counted in 32 bit words, which is how the generator hands them out
+impl RngCore for Random {
+    fn next_u32(&mut self) -> u32 {
+        self.draws += 1;
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.draws += 2;
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.draws += (dest.len() as u64).div_ceil(4);
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
 // Since Default for Option is None, this works without needing register_resourcesR
 /*
 pub fn register_resources(specs_world: &mut World) {
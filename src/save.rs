@@ -0,0 +1,523 @@
+//! Saving the whole game world to a file, and loading it back.
+//!
+//! A save file is plain text. After the header come the level, the clock, the camera,
+//! the formation for move orders and the navigation grid, then one block per entity
+//! listing its components:
+//!
+//! ```text
+//! ddu2020 save 11
+//! level test.png
+//! seed 1234
+//! tick 600
+//! random 36
+//! camera -16 -236 1
+//! formation Grid
+//! navgrid 40 40
+//! 1 1 # # 0.5 ...
+//! entity 17
+//! position 96 64
+//! motion 0 0
+//! renderable rect 20 20 0 0 1 1
+//! unit false goto 41.5 0 128 64 160 96
+//! groups 1 3
+//! team Player
+//! health 100 100
+//! weapon 32 10 1 0
+//! mass 1
+//! shape circle 20 0 0
+//! hitbox 96 64 Ground Wall+Ground+Projectile+Trigger+Water
+//! ```
+//!
+//! The random line counts how far the random number generator has come since it was seeded.
+//! A goto order is how close the unit has got to its destination and how many ticks ago, then
+//! the waypoints. An attack order is the target, how many ticks until the way to it is found again
+//! and the cell the target was in when it last was (or none), then the waypoints.
+//! A shape line is the size of the hitbox and how far it is off the entity's position.
+//! A hitbox line is where the middle of the hitbox is, its layer and mask, then the id
+//! of its owner if it has one. It always comes after the shape line.
+//! Entities are referred to by the id they had when the game was saved, they are given
+//! new ids when loading.
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::{error, fmt, io};
+
+use collider::geom::v2;
+use ggez_goodies::{Point2, Vector2};
+use specs::{world::Builder, Entity, Join, WorldExt};
+
+use crate::components::*;
+use crate::formation::Formation;
+use crate::pathfinding::NavGrid;
+use crate::physics::{add_hitbox, ColliderRes, Layers, NoColliderError, Profile};
+use crate::resources::{Camera, Clock, ControlGroups, Random, CONTROL_GROUPS};
+use crate::world::Simulation;
+
+// First line of every save file. The number goes up whenever the format changes.
+const HEADER: &str = "ddu2020 save 11";
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    // The file doesn't start with the header, or was made by a different version
+    BadHeader,
+    // A line couldn't be read. Lines are counted from 1.
+    BadLine(usize, String),
+    // The file ended in the middle of the navigation grid
+    Truncated,
+    NoCollider,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "{}", err),
+            SaveError::BadHeader => write!(f, "not a save file, or from a different version"),
+            SaveError::BadLine(line, text) => write!(f, "can't read line {}: {:?}", line, text),
+            SaveError::Truncated => write!(f, "the save file ends too early"),
+            SaveError::NoCollider => write!(f, "the collider hasn't been set up yet"),
+        }
+    }
+}
+
+impl error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SaveError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<NoColliderError> for SaveError {
+    fn from(_: NoColliderError) -> Self {
+        SaveError::NoCollider
+    }
+}
+
+// Write the simulation to a file. The simulation itself is left as it was.
+pub fn save<P: AsRef<Path>>(simulation: &Simulation, path: P) -> io::Result<()> {
+    // Writing to a String can't fail
+    let text = write_world(simulation).unwrap();
+    fs::write(path, text)
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Simulation, SaveError> {
+    read_world(&fs::read_to_string(path)?)
+}
+
+fn write_world(simulation: &Simulation) -> Result<String, fmt::Error> {
+    let world = simulation.specs_world();
+    let mut f = String::new();
+
+    writeln!(f, "{}", HEADER)?;
+    writeln!(f, "level {}", simulation.level())?;
+    writeln!(f, "seed {}", simulation.seed())?;
+    writeln!(f, "tick {}", simulation.tick())?;
+    writeln!(f, "random {}", world.fetch::<Random>().draws())?;
+
+    let camera = world.fetch::<Camera>();
+    writeln!(f, "camera {} {} {}", camera.offset.x, camera.offset.y, camera.zoom)?;
+    writeln!(f, "formation {}", world.fetch::<Formation>().name())?;
+
+    // One row of cells per line, # for blocked cells and the walking speed for the rest
+    let nav_grid = world.fetch::<NavGrid>();
+    writeln!(f, "navgrid {} {}", nav_grid.width(), nav_grid.height())?;
+    for y in 0..nav_grid.height() {
+        let row: Vec<String> = (0..nav_grid.width())
+            .map(|x| match nav_grid.is_blocked(x as isize, y as isize) {
+                true => "#".to_string(),
+                false => nav_grid.speed(x, y).to_string(),
+            })
+            .collect();
+        writeln!(f, "{}", row.join(" "))?;
+    }
+
+    let entities = world.entities();
+    let position = world.read_storage::<Position>();
+    let motion = world.read_storage::<Motion>();
+    let renderable = world.read_storage::<Renderable>();
+    let terrain = world.read_storage::<Terrain>();
+    let resource_node = world.read_storage::<ResourceNode>();
+    let unit = world.read_storage::<Unit>();
+    let team = world.read_storage::<Team>();
+    let health = world.read_storage::<Health>();
+    let weapon = world.read_storage::<Weapon>();
+    let mass = world.read_storage::<Mass>();
+    let enemy_ai = world.read_storage::<EnemyAi>();
+    let collider_shape = world.read_storage::<ColliderShape>();
+    let box_collider = world.read_storage::<BoxCollider>();
+    let collider = world.fetch::<Option<ColliderRes>>();
+    let control_groups = world.fetch::<ControlGroups>();
+
+    for entity in entities.join() {
+        writeln!(f, "entity {}", entity.id())?;
+
+        if let Some(Position(pos)) = position.get(entity) {
+            writeln!(f, "position {} {}", pos.x, pos.y)?;
+        }
+        if let Some(motion) = motion.get(entity) {
+            writeln!(f, "motion {} {}", motion.velocity.x, motion.velocity.y)?;
+        }
+        match renderable.get(entity) {
+            Some(Renderable::Rectangle { w, h, color }) => writeln!(
+                f,
+                "renderable rect {} {} {} {} {} {}",
+                w, h, color.r, color.g, color.b, color.a
+            )?,
+            Some(Renderable::SpriteId(id)) => writeln!(f, "renderable sprite {}", id)?,
+            None => (),
+        }
+        if let Some(terrain) = terrain.get(entity) {
+            writeln!(f, "terrain {:?}", terrain)?;
+        }
+        if let Some(resource_node) = resource_node.get(entity) {
+            writeln!(f, "resource {}", resource_node.amount)?;
+        }
+        if let Some(unit) = unit.get(entity) {
+            write!(f, "unit {}", unit.selected)?;
+            // Targets that have died since they were picked are saved as no target at all
+            match &unit.action {
+                Action::Attack { target, waypoints, target_cell, repath_in } if entities.is_alive(*target) => {
+                    write!(f, " attack {} {}", target.id(), repath_in)?;
+                    match target_cell {
+                        Some((x, y)) => write!(f, " {} {}", x, y)?,
+                        None => write!(f, " none")?,
+                    }
+                    for waypoint in waypoints {
+                        write!(f, " {} {}", waypoint.x, waypoint.y)?;
+                    }
+                }
+                Action::Hold => write!(f, " hold")?,
+                Action::Goto { waypoints, closest, stalled } => {
+                    write!(f, " goto {} {}", closest, stalled)?;
+                    for waypoint in waypoints {
+                        write!(f, " {} {}", waypoint.x, waypoint.y)?;
+                    }
+                }
+                _ => write!(f, " standby")?,
+            }
+            writeln!(f)?;
+        }
+        // The control groups the unit is in, counted from 1 like the keys
+        let groups: Vec<String> = (0..CONTROL_GROUPS)
+            .filter(|&i| control_groups.groups[i].contains(&entity))
+            .map(|i| (i + 1).to_string())
+            .collect();
+        if !groups.is_empty() {
+            writeln!(f, "groups {}", groups.join(" "))?;
+        }
+        if let Some(team) = team.get(entity) {
+            writeln!(f, "team {:?}", team)?;
+        }
+        if let Some(health) = health.get(entity) {
+            writeln!(f, "health {} {}", health.current, health.max)?;
+        }
+        if let Some(weapon) = weapon.get(entity) {
+            writeln!(f, "weapon {} {} {} {}", weapon.range, weapon.damage, weapon.cooldown, weapon.ready_in)?;
+        }
+        if let Some(Mass(mass)) = mass.get(entity) {
+            writeln!(f, "mass {}", mass)?;
+        }
+        if let Some(enemy_ai) = enemy_ai.get(entity) {
+            match enemy_ai.state {
+                AiState::Chase(target) if entities.is_alive(target) => {
+                    writeln!(f, "enemy_ai {} chase {}", enemy_ai.sight_radius, target.id())?
+                }
+                _ => writeln!(f, "enemy_ai {} idle", enemy_ai.sight_radius)?,
+            }
+        }
+        match collider_shape.get(entity) {
+            Some(ColliderShape::Circle { diameter, offset }) => {
+                writeln!(f, "shape circle {} {} {}", diameter, offset.x, offset.y)?
+            }
+            Some(ColliderShape::Rect { w, h, offset }) => writeln!(f, "shape rect {} {} {} {}", w, h, offset.x, offset.y)?,
+            None => (),
+        }
+        if let (Some(box_collider), Some(collider)) = (box_collider.get(entity), collider.as_ref()) {
+            // The hitbox position is written as well, the entity's position is rounded off
+            let pos = collider.get_hitbox(box_collider.id()).value.pos;
+            let profile = box_collider.0;
+            write!(f, "hitbox {} {} {} {}", pos.x, pos.y, profile.layer.name(), profile.mask.name())?;
+            // Owners that have died since are left out
+            match profile.owner.map(|id| entities.entity(id)) {
+                Some(owner) if entities.is_alive(owner) => writeln!(f, " {}", owner.id())?,
+                _ => writeln!(f)?,
+            }
+        }
+    }
+
+    Ok(f)
+}
+
+// Everything about one entity in the file, with the line number each component was on
+struct SavedEntity<'a> {
+    id: u32,
+    components: Vec<(usize, &'a str)>,
+}
+
+fn read_world(text: &str) -> Result<Simulation, SaveError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    if lines.next().map(|(_, line)| line) != Some(HEADER) {
+        return Err(SaveError::BadHeader);
+    }
+
+    // The header lines come in a fixed order
+    let mut field = |name: &str| {
+        let (number, line) = lines.next().ok_or(SaveError::Truncated)?;
+        line.strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix(' '))
+            .ok_or_else(|| SaveError::BadLine(number, line.to_string()))
+            .map(|rest| (number, rest))
+    };
+    let bad = |(number, line): (usize, &str)| SaveError::BadLine(number, line.to_string());
+
+    let (_, level) = field("level")?;
+    let seed = field("seed").and_then(|(n, rest)| rest.parse::<u64>().map_err(|_| bad((n, rest))))?;
+    let tick = field("tick").and_then(|(n, rest)| rest.parse::<u64>().map_err(|_| bad((n, rest))))?;
+    let random_draws = field("random").and_then(|(n, rest)| rest.parse::<u64>().map_err(|_| bad((n, rest))))?;
+
+    let (number, camera) = field("camera")?;
+    let camera: Vec<f32> = parse_all(camera).ok_or_else(|| bad((number, camera)))?;
+    let (camera_offset, camera_zoom) = match camera[..] {
+        [x, y, zoom] => (Point2::new(x, y), zoom),
+        _ => return Err(bad((number, "camera"))),
+    };
+
+    let (number, formation) = field("formation")?;
+    let formation = Formation::from_name(formation).ok_or_else(|| bad((number, formation)))?;
+
+    let (number, size) = field("navgrid")?;
+    let size: Vec<usize> = parse_all(size).ok_or_else(|| bad((number, size)))?;
+    let (width, height) = match size[..] {
+        [width, height] => (width, height),
+        _ => return Err(bad((number, "navgrid"))),
+    };
+
+    let mut nav_grid = NavGrid::new(width, height);
+    for y in 0..height {
+        let (number, row) = lines.next().ok_or(SaveError::Truncated)?;
+        let cells: Vec<&str> = row.split_whitespace().collect();
+        if cells.len() != width {
+            return Err(bad((number, row)));
+        }
+
+        for (x, cell) in cells.into_iter().enumerate() {
+            match cell {
+                "#" => nav_grid.set_blocked(x, y, true),
+                speed => nav_grid.set_speed(x, y, speed.parse().map_err(|_| bad((number, row)))?),
+            }
+        }
+    }
+
+    // Group the rest of the lines by entity
+    let mut saved_entities: Vec<SavedEntity> = Vec::new();
+    for (number, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(id) = line.strip_prefix("entity ") {
+            let id = id.parse().map_err(|_| bad((number, line)))?;
+            saved_entities.push(SavedEntity { id, components: Vec::new() });
+        } else {
+            let entity = saved_entities.last_mut().ok_or_else(|| bad((number, line)))?;
+            entity.components.push((number, line));
+        }
+    }
+
+    let mut simulation = Simulation::empty(level, seed);
+    let world = simulation.specs_world_mut();
+
+    world.fetch_mut::<Clock>().tick = tick;
+    world.fetch_mut::<Random>().restore(random_draws);
+    {
+        let mut camera = world.fetch_mut::<Camera>();
+        camera.offset = camera_offset;
+        camera.zoom = camera_zoom;
+    }
+    world.insert(formation);
+    world.insert(nav_grid);
+
+    // The collider has to be at the saved time before hitboxes are added,
+    // otherwise the next tick would move everything by the whole time since the start
+    let time = world.fetch::<Clock>().time();
+    world.fetch_mut::<Option<ColliderRes>>().as_mut().ok_or(SaveError::NoCollider)?.set_time(time);
+
+    // Create every entity first, so they can refer to each other
+    let ids: HashMap<u32, Entity> = saved_entities.iter()
+        .map(|saved| (saved.id, world.create_entity().build()))
+        .collect();
+
+    for saved in &saved_entities {
+        let entity = ids[&saved.id];
+
+        for &(number, line) in &saved.components {
+            read_component(entity, line, &ids, world)
+                .ok_or_else(|| bad((number, line)))??;
+        }
+    }
+
+    Ok(simulation)
+}
+
+// Add one component from a save file line to the entity.
+// Returns None if the line can't be read.
+fn read_component(
+    entity: Entity,
+    line: &str,
+    ids: &HashMap<u32, Entity>,
+    world: &mut specs::World,
+) -> Option<Result<(), SaveError>> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let float = |i: usize| words.get(i).and_then(|word| word.parse::<f32>().ok());
+    let target = |i: usize| words.get(i).and_then(|word| word.parse::<u32>().ok()).and_then(|id| ids.get(&id)).cloned();
+
+    // Unwrapping the inserts is fine, the entity was just created
+    match words[..] {
+        ["position", _, _] => {
+            let pos = Position(Point2::new(float(1)?, float(2)?));
+            world.write_storage().insert(entity, pos).unwrap();
+        }
+        ["motion", _, _] => {
+            let motion = Motion { velocity: Vector2::new(float(1)?, float(2)?) };
+            world.write_storage().insert(entity, motion).unwrap();
+        }
+        ["renderable", "rect", _, _, _, _, _, _] => {
+            let renderable = Renderable::Rectangle {
+                w: float(2)?,
+                h: float(3)?,
+                color: ggez::graphics::Color::new(float(4)?, float(5)?, float(6)?, float(7)?),
+            };
+            world.write_storage().insert(entity, renderable).unwrap();
+        }
+        ["renderable", "sprite", id] => {
+            let renderable = Renderable::SpriteId(id.parse().ok()?);
+            world.write_storage().insert(entity, renderable).unwrap();
+        }
+        ["terrain", terrain] => {
+            let terrain = match terrain {
+                "Wall" => Terrain::Wall,
+                "Water" => Terrain::Water,
+                "Mud" => Terrain::Mud,
+                "Floor" => Terrain::Floor,
+                _ => return None,
+            };
+            world.write_storage().insert(entity, terrain).unwrap();
+        }
+        ["resource", amount] => {
+            let resource_node = ResourceNode { amount: amount.parse().ok()? };
+            world.write_storage().insert(entity, resource_node).unwrap();
+        }
+        ["unit", selected, ref action @ ..] => {
+            let action = match action {
+                ["standby"] => Action::Standby,
+                ["attack", _, repath_in, ref rest @ ..] => {
+                    let (target_cell, waypoints) = match rest {
+                        ["none", ref waypoints @ ..] => (None, waypoints),
+                        [x, y, ref waypoints @ ..] => (Some((x.parse().ok()?, y.parse().ok()?)), waypoints),
+                        _ => return None,
+                    };
+                    if waypoints.len() % 2 != 0 {
+                        return None;
+                    }
+                    let coordinates: Vec<f32> = parse_all(&waypoints.join(" "))?;
+                    Action::Attack {
+                        target: target(3)?,
+                        waypoints: coordinates.chunks(2).map(|xy| Point2::new(xy[0], xy[1])).collect(),
+                        target_cell,
+                        repath_in: repath_in.parse().ok()?,
+                    }
+                }
+                ["hold"] => Action::Hold,
+                ["goto", closest, stalled, ref waypoints @ ..] if waypoints.len() % 2 == 0 => {
+                    let coordinates: Vec<f32> = parse_all(&waypoints.join(" "))?;
+                    Action::Goto {
+                        waypoints: coordinates.chunks(2).map(|xy| Point2::new(xy[0], xy[1])).collect(),
+                        closest: closest.parse().ok()?,
+                        stalled: stalled.parse().ok()?,
+                    }
+                }
+                _ => return None,
+            };
+            let unit = Unit { selected: selected.parse().ok()?, action };
+            world.write_storage().insert(entity, unit).unwrap();
+        }
+        ["groups", ref numbers @ ..] if !numbers.is_empty() => {
+            let mut control_groups = world.fetch_mut::<ControlGroups>();
+            for number in parse_all::<usize>(&numbers.join(" "))? {
+                control_groups.groups.get_mut(number.checked_sub(1)?)?.push(entity);
+            }
+        }
+        ["team", team] => {
+            let team = match team {
+                "Player" => Team::Player,
+                "Enemy" => Team::Enemy,
+                _ => return None,
+            };
+            world.write_storage().insert(entity, team).unwrap();
+        }
+        ["health", _, _] => {
+            let health = Health { current: float(1)?, max: float(2)? };
+            world.write_storage().insert(entity, health).unwrap();
+        }
+        ["weapon", _, _, _, _] => {
+            let weapon = Weapon {
+                range: float(1)?,
+                damage: float(2)?,
+                cooldown: float(3)?,
+                ready_in: float(4)?,
+            };
+            world.write_storage().insert(entity, weapon).unwrap();
+        }
+        ["mass", _] => {
+            world.write_storage().insert(entity, Mass(float(1)?)).unwrap();
+        }
+        ["enemy_ai", _, ref state @ ..] => {
+            let state = match state {
+                ["idle"] => AiState::Idle,
+                ["chase", _] => AiState::Chase(target(3)?),
+                _ => return None,
+            };
+            let enemy_ai = EnemyAi { state, sight_radius: float(1)? };
+            world.write_storage().insert(entity, enemy_ai).unwrap();
+        }
+        ["shape", "circle", _, _, _] => {
+            let shape = ColliderShape::circle(float(2)?).with_offset(Vector2::new(float(3)?, float(4)?));
+            world.write_storage().insert(entity, shape).unwrap();
+        }
+        ["shape", "rect", _, _, _, _] => {
+            let shape = ColliderShape::rect(float(2)?, float(3)?).with_offset(Vector2::new(float(4)?, float(5)?));
+            world.write_storage().insert(entity, shape).unwrap();
+        }
+        ["hitbox", _, _, layer, mask, ref owner @ ..] if owner.len() <= 1 => {
+            let number = |i: usize| words.get(i).and_then(|word| word.parse::<f64>().ok());
+            let shape = world.read_storage::<ColliderShape>().get(entity).copied()?;
+            let hitbox = shape.shape().place(v2(number(1)?, number(2)?)).still();
+
+            let mut profile = Profile::new(entity, Layers::from_name(layer)?).with_mask(Layers::from_name(mask)?);
+            if !owner.is_empty() {
+                profile = profile.with_owner(target(5)?);
+            }
+
+            return Some(add_hitbox(entity, hitbox, profile, world).map_err(SaveError::from));
+        }
+        _ => return None,
+    }
+
+    Some(Ok(()))
+}
+
+// Parse every word of a line, or None if any of them can't be parsed.
+fn parse_all<T: FromStr>(text: &str) -> Option<Vec<T>> {
+    text.split_whitespace().map(|word| word.parse().ok()).collect()
+}
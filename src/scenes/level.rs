@@ -0,0 +1,134 @@
+//! Playing a level. This is the game itself, Quit opens the pause menu on top of it.
+use std::cell::Cell;
+use std::rc::Rc;
+
+use ggez::event::KeyMods;
+use ggez::Context;
+
+use super::pause::PauseMenu;
+use super::{Scene, Switch};
+use crate::input::{self, Button};
+use crate::world::{Simulation, World};
+
+// Where QuickSave saves the game to, and QuickLoad loads it from
+pub const QUICKSAVE_PATH: &str = "quicksave.txt";
+
+pub struct LevelScene {
+    world: World,
+    // Where the replay is saved when the level is left, if it is being recorded
+    record_path: Option<String>,
+    // QuickLoad was pressed, the saved game replaces this one on the next update
+    quick_load: bool,
+    // Quit was pressed, the pause menu opens on the next update
+    pause: bool,
+    // Set by the pause menu when Exit is picked in it
+    exit: Rc<Cell<bool>>,
+}
+
+impl LevelScene {
+    pub fn new(ctx: &mut Context, simulation: Simulation) -> Self {
+        LevelScene {
+            world: World::with_simulation(ctx, simulation),
+            record_path: None,
+            quick_load: false,
+            pause: false,
+            exit: Rc::new(Cell::new(false)),
+        }
+    }
+
+    // Record everything from now on, and save it to the path when the level is left.
+    pub fn record(mut self, path: &str) -> Self {
+        self.world.simulation_mut().start_recording();
+        self.record_path = Some(path.to_string());
+        self
+    }
+
+    // Saving and loading happen outside of the simulation, so they don't end up in replays
+    fn quick_save(&mut self) {
+        match self.world.simulation_mut().save(QUICKSAVE_PATH) {
+            Ok(_) => println!("Saved game to {}", QUICKSAVE_PATH),
+            Err(e) => println!("Couldn't save game to {}: {}", QUICKSAVE_PATH, e),
+        }
+    }
+
+    // A replay can only start from the level itself, so loading isn't allowed while one is recorded or
+    // played back. What was recorded before the load wouldn't lead up to the saved game when played.
+    fn quick_load(&mut self, ctx: &mut Context) {
+        let simulation = self.world.simulation();
+        if simulation.recording().is_some() || simulation.is_replaying() {
+            println!("Can't load a saved game during a replay or while recording one");
+            return;
+        }
+
+        match Simulation::load(QUICKSAVE_PATH) {
+            Ok(simulation) => self.world = World::with_simulation(ctx, simulation),
+            Err(e) => println!("Couldn't load game from {}: {}", QUICKSAVE_PATH, e),
+        }
+    }
+}
+
+impl Scene for LevelScene {
+    fn update(&mut self, ctx: &mut Context) -> Switch {
+        if self.exit.get() {
+            return Switch::Pop;
+        }
+        if self.pause {
+            self.pause = false;
+            return Switch::push(PauseMenu::new(ctx, self.exit.clone()));
+        }
+        if self.quick_load {
+            self.quick_load = false;
+            self.quick_load(ctx);
+        }
+
+        self.world.update();
+
+        Switch::None
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> ggez::GameResult<()> {
+        self.world.draw(ctx)
+    }
+
+    fn input(&mut self, ev: input::Event, started: bool) {
+        match (ev, started) {
+            (input::Event::Button(Button::QuickSave), true) => self.quick_save(),
+            (input::Event::Button(Button::QuickLoad), true) => self.quick_load = true,
+            (input::Event::Button(Button::QuickSave), false) | (input::Event::Button(Button::QuickLoad), false) => (),
+            (input::Event::Button(Button::Quit), true) => self.pause = true,
+            _ => self.world.handle_input(ev, started),
+        }
+    }
+
+    fn mouse_motion(&mut self, x: f32, y: f32) {
+        self.world.handle_mouse_motion(x, y);
+    }
+
+    fn axis_impulse(&mut self, axis: input::Axis, amount: f32) {
+        self.world.handle_axis_impulse(axis, amount);
+    }
+
+    fn modifiers(&mut self, modifiers: KeyMods) {
+        self.world.handle_modifiers(modifiers);
+    }
+
+    fn resize(&mut self, width: f32, height: f32) {
+        self.world.handle_resize(width, height);
+    }
+
+    fn name(&self) -> &str {
+        "Level"
+    }
+}
+
+// Save what was recorded however the level is left, even if the game stopped with an error
+impl Drop for LevelScene {
+    fn drop(&mut self) {
+        if let (Some(path), Some(recording)) = (&self.record_path, self.world.simulation().recording()) {
+            match recording.save(path) {
+                Ok(_) => println!("Saved replay to {}", path),
+                Err(e) => println!("Couldn't save replay to {}: {}", path, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Picking which room in `resources/rooms` to play.
+use ggez::Context;
+
+use super::level::LevelScene;
+use super::menu::{Menu, MenuAction};
+use super::{Scene, Switch};
+use crate::{input, level, world};
+
+pub struct LevelSelect {
+    menu: Menu,
+    levels: Vec<String>,
+    action: Option<MenuAction>,
+}
+
+impl LevelSelect {
+    pub fn new(ctx: &mut Context) -> Self {
+        let (levels, message) = match level::list_levels() {
+            Ok(levels) if levels.is_empty() => (levels, Some(format!("No levels in {}", level::LEVEL_DIR))),
+            Ok(levels) => (levels, None),
+            Err(e) => (Vec::new(), Some(format!("Couldn't read {}: {}", level::LEVEL_DIR, e))),
+        };
+
+        // The last button goes back to the main menu
+        let mut options = levels.clone();
+        options.push("Back".to_string());
+
+        let mut menu = Menu::new(ctx, "Pick a level", options);
+        menu.set_message(message);
+
+        LevelSelect {
+            menu,
+            levels,
+            action: None,
+        }
+    }
+}
+
+impl Scene for LevelSelect {
+    fn update(&mut self, ctx: &mut Context) -> Switch {
+        match self.action.take() {
+            Some(MenuAction::Pick(i)) if i < self.levels.len() => {
+                let level = &self.levels[i];
+                match world::Simulation::new(level) {
+                    // Playing replaces the level select, so leaving the level goes back to the main menu
+                    Ok(simulation) => Switch::replace(LevelScene::new(ctx, simulation)),
+                    Err(e) => {
+                        self.menu.set_message(Some(format!("Couldn't load {}: {}", level, e)));
+                        Switch::None
+                    }
+                }
+            }
+            Some(_) => Switch::Pop,
+            None => Switch::None,
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> ggez::GameResult<()> {
+        self.menu.draw(ctx)
+    }
+
+    fn input(&mut self, ev: input::Event, started: bool) {
+        if let Some(action) = self.menu.input(ev, started) {
+            self.action = Some(action);
+        }
+    }
+
+    fn mouse_motion(&mut self, x: f32, y: f32) {
+        self.menu.mouse_motion(x, y);
+    }
+
+    fn resize(&mut self, width: f32, height: f32) {
+        self.menu.resize(width, height);
+    }
+
+    fn name(&self) -> &str {
+        "Level select"
+    }
+}
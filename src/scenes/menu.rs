@@ -0,0 +1,224 @@
+//! A list of buttons to pick from, and the main menu built from it.
+use ggez::graphics::*;
+use ggez::Context;
+use ggez_goodies::{Point2, Vector2};
+
+use super::{level_select::LevelSelect, Scene, Switch};
+use crate::input::{self, Axis, Button};
+
+// Size of the buttons, in pixels
+const BUTTON_WIDTH: f32 = 240.0;
+const BUTTON_HEIGHT: f32 = 40.0;
+
+// What the player did in a menu
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MenuAction {
+    // The option with this index was picked
+    Pick(usize),
+    // Quit was pressed, the menu should close
+    Back,
+}
+
+// A title with a column of buttons under it. They can be picked with the mouse,
+// or by moving up and down and pressing Confirm.
+pub struct Menu {
+    title: String,
+    options: Vec<String>,
+    selected: usize,
+    // Shown under the buttons, for things like errors
+    message: Option<String>,
+    // Where the mouse is, in window pixels
+    mouse: Point2,
+    // The whole window, in pixels
+    screen: Rect,
+    square: Mesh,
+}
+
+impl Menu {
+    pub fn new(ctx: &mut Context, title: &str, options: Vec<String>) -> Self {
+        let square = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(0.0, 0.0, 1.0, 1.0),
+            Color::new(1.0, 1.0, 1.0, 1.0)
+        ).unwrap();
+
+        let (w, h) = drawable_size(ctx);
+        let mouse = ggez::input::mouse::position(ctx);
+
+        Menu {
+            title: title.to_string(),
+            options,
+            selected: 0,
+            message: None,
+            mouse: Point2::new(mouse.x, mouse.y),
+            screen: Rect::new(0.0, 0.0, w, h),
+            square,
+        }
+    }
+
+    pub fn set_message(&mut self, message: Option<String>) {
+        self.message = message;
+    }
+
+    // Where the buttons are on the screen, one above the other in the middle
+    fn buttons(&self) -> Vec<Rect> {
+        let x = self.screen.x + (self.screen.w - BUTTON_WIDTH) / 2.0;
+        let y = self.screen.y + self.screen.h / 3.0 + BUTTON_HEIGHT * 1.5;
+
+        (0..self.options.len())
+            .map(|i| Rect::new(x, y + BUTTON_HEIGHT * 1.5 * i as f32, BUTTON_WIDTH, BUTTON_HEIGHT))
+            .collect()
+    }
+
+    pub fn draw(&self, ctx: &mut Context) -> ggez::GameResult<()> {
+        let previous = screen_coordinates(ctx);
+        set_screen_coordinates(ctx, self.screen)?;
+
+        let title = Text::new(self.title.as_str());
+        let (w, _) = title.dimensions(ctx);
+        draw(
+            ctx,
+            &title,
+            DrawParam::default().dest(Point2::new(
+                self.screen.x + (self.screen.w - w as f32) / 2.0,
+                self.screen.y + self.screen.h / 3.0,
+            ))
+        )?;
+
+        let buttons = self.buttons();
+        for (i, (option, rect)) in self.options.iter().zip(&buttons).enumerate() {
+            let color = match i == self.selected {
+                true => Color::new(0.5, 0.5, 0.7, 1.0),
+                false => Color::new(0.3, 0.3, 0.4, 1.0),
+            };
+            draw(
+                ctx,
+                &self.square,
+                DrawParam::default()
+                    .dest(Point2::new(rect.x, rect.y))
+                    .scale(Vector2::new(rect.w, rect.h))
+                    .color(color)
+            )?;
+
+            let label = Text::new(option.as_str());
+            let (w, h) = label.dimensions(ctx);
+            draw(
+                ctx,
+                &label,
+                DrawParam::default().dest(Point2::new(
+                    rect.x + (rect.w - w as f32) / 2.0,
+                    rect.y + (rect.h - h as f32) / 2.0,
+                ))
+            )?;
+        }
+
+        if let Some(message) = &self.message {
+            let text = Text::new(message.as_str());
+            let (w, _) = text.dimensions(ctx);
+            let y = buttons.last().map_or(self.screen.h / 2.0, |rect| rect.bottom() + BUTTON_HEIGHT);
+            draw(
+                ctx,
+                &text,
+                DrawParam::default()
+                    .dest(Point2::new(self.screen.x + (self.screen.w - w as f32) / 2.0, y))
+                    .color(Color::new(1.0, 0.6, 0.6, 1.0))
+            )?;
+        }
+
+        set_screen_coordinates(ctx, previous)?;
+
+        Ok(())
+    }
+
+    pub fn input(&mut self, ev: input::Event, started: bool) -> Option<MenuAction> {
+        use input::Event;
+
+        if !started {
+            return None;
+        }
+
+        match ev {
+            Event::Button(Button::Quit) => Some(MenuAction::Back),
+            _ if self.options.is_empty() => None,
+            Event::Button(Button::Confirm) => Some(MenuAction::Pick(self.selected)),
+            Event::Button(Button::Left) => {
+                let clicked = self.buttons().iter().position(|rect| rect.contains(self.mouse));
+                clicked.map(MenuAction::Pick)
+            }
+            // Up and down move through the options, wrapping around at the ends
+            Event::Axis(Axis::PanY, positive) => {
+                let count = self.options.len();
+                self.selected = match positive {
+                    true => (self.selected + 1) % count,
+                    false => (self.selected + count - 1) % count,
+                };
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn mouse_motion(&mut self, x: f32, y: f32) {
+        self.mouse = Point2::new(x, y);
+
+        if let Some(i) = self.buttons().iter().position(|rect| rect.contains(self.mouse)) {
+            self.selected = i;
+        }
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.screen = Rect::new(0.0, 0.0, width, height);
+    }
+}
+
+// The first thing shown when the game starts.
+pub struct MainMenu {
+    menu: Menu,
+    action: Option<MenuAction>,
+}
+
+const PLAY: usize = 0;
+const QUIT: usize = 1;
+
+impl MainMenu {
+    pub fn new(ctx: &mut Context) -> Self {
+        MainMenu {
+            menu: Menu::new(ctx, "ddu2020", vec!["Play".to_string(), "Quit".to_string()]),
+            action: None,
+        }
+    }
+}
+
+impl Scene for MainMenu {
+    fn update(&mut self, ctx: &mut Context) -> Switch {
+        match self.action.take() {
+            Some(MenuAction::Pick(PLAY)) => Switch::push(LevelSelect::new(ctx)),
+            // Leaving the main menu empties the stack, which closes the game
+            Some(MenuAction::Pick(QUIT)) | Some(MenuAction::Back) => Switch::Pop,
+            _ => Switch::None,
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> ggez::GameResult<()> {
+        self.menu.draw(ctx)
+    }
+
+    fn input(&mut self, ev: input::Event, started: bool) {
+        if let Some(action) = self.menu.input(ev, started) {
+            self.action = Some(action);
+        }
+    }
+
+    fn mouse_motion(&mut self, x: f32, y: f32) {
+        self.menu.mouse_motion(x, y);
+    }
+
+    fn resize(&mut self, width: f32, height: f32) {
+        self.menu.resize(width, height);
+    }
+
+    fn name(&self) -> &str {
+        "Main menu"
+    }
+}
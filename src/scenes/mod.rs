@@ -0,0 +1,163 @@
+//! Scenes are the screens of the game, like the main menu or a level being played.
+//! They are kept on a stack: only the top one gets input and is updated, but the ones
+//! below it can still be drawn, so a menu can be shown on top of a level. Buttons being
+//! let go of and modifier keys reach every scene, so nothing stays held down underneath.
+//!
+//! This is the scene stack from ggez-goodies, with mouse and window events added.
+use ggez::event::KeyMods;
+use ggez::{Context, GameResult};
+
+use crate::input;
+
+pub mod menu;
+pub mod level_select;
+pub mod level;
+pub mod pause;
+
+// What the stack should do after a scene has been updated.
+pub enum Switch {
+    None,
+    Push(Box<dyn Scene>),
+    Replace(Box<dyn Scene>),
+    Pop,
+}
+
+impl Switch {
+    pub fn push<S: Scene + 'static>(scene: S) -> Self {
+        Switch::Push(Box::new(scene))
+    }
+
+    pub fn replace<S: Scene + 'static>(scene: S) -> Self {
+        Switch::Replace(Box::new(scene))
+    }
+}
+
+pub trait Scene {
+    fn update(&mut self, ctx: &mut Context) -> Switch;
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()>;
+    fn input(&mut self, ev: input::Event, started: bool);
+
+    // Mouse position in window pixels
+    fn mouse_motion(&mut self, _x: f32, _y: f32) {}
+    fn axis_impulse(&mut self, _axis: input::Axis, _amount: f32) {}
+    // Sent before every key and mouse button, with the modifier keys held at the time.
+    // Like button releases, these reach every scene on the stack.
+    fn modifiers(&mut self, _modifiers: KeyMods) {}
+    // Every scene on the stack is told when the window changes size, not just the top one
+    fn resize(&mut self, _width: f32, _height: f32) {}
+
+    fn name(&self) -> &str;
+
+    // Whether the scene below this one should be drawn first
+    fn draw_previous(&self) -> bool {
+        false
+    }
+}
+
+pub struct Stack {
+    scenes: Vec<Box<dyn Scene>>,
+}
+
+impl Stack {
+    pub fn new() -> Self {
+        Stack {
+            scenes: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, scene: Box<dyn Scene>) {
+        self.scenes.push(scene)
+    }
+
+    pub fn pop(&mut self) -> Option<Box<dyn Scene>> {
+        self.scenes.pop()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    // Name of the scene on top, if there is one
+    pub fn current(&self) -> Option<&str> {
+        self.scenes.last().map(|scene| scene.name())
+    }
+
+    // Apply a switch. Returns the scene that was removed, if any.
+    pub fn switch(&mut self, next_scene: Switch) -> Option<Box<dyn Scene>> {
+        match next_scene {
+            Switch::None => None,
+            Switch::Pop => self.pop(),
+            Switch::Push(scene) => {
+                self.push(scene);
+                None
+            }
+            Switch::Replace(scene) => {
+                let old_scene = self.pop();
+                self.push(scene);
+                old_scene
+            }
+        }
+    }
+
+    pub fn update(&mut self, ctx: &mut Context) {
+        if let Some(scene) = self.scenes.last_mut() {
+            let next_scene = scene.update(ctx);
+            self.switch(next_scene);
+        }
+    }
+
+    fn draw_scenes(scenes: &mut [Box<dyn Scene>], ctx: &mut Context) -> GameResult<()> {
+        if let Some((current, rest)) = scenes.split_last_mut() {
+            if current.draw_previous() {
+                Self::draw_scenes(rest, ctx)?;
+            }
+            current.draw(ctx)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        Self::draw_scenes(&mut self.scenes, ctx)
+    }
+
+    pub fn input(&mut self, ev: input::Event, started: bool) {
+        if !started {
+            for scene in &mut self.scenes {
+                scene.input(ev, started);
+            }
+        } else if let Some(scene) = self.scenes.last_mut() {
+            scene.input(ev, started);
+        }
+    }
+
+    pub fn mouse_motion(&mut self, x: f32, y: f32) {
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.mouse_motion(x, y);
+        }
+    }
+
+    pub fn axis_impulse(&mut self, axis: input::Axis, amount: f32) {
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.axis_impulse(axis, amount);
+        }
+    }
+
+    pub fn modifiers(&mut self, modifiers: KeyMods) {
+        for scene in &mut self.scenes {
+            scene.modifiers(modifiers);
+        }
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32) {
+        for scene in &mut self.scenes {
+            scene.resize(width, height);
+        }
+    }
+}
+
+impl Default for Stack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
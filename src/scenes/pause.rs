@@ -0,0 +1,100 @@
+//! The pause menu, shown on top of the level it paused.
+use std::cell::Cell;
+use std::rc::Rc;
+
+use ggez::graphics::*;
+use ggez::Context;
+use ggez_goodies::{Point2, Vector2};
+
+use super::menu::{Menu, MenuAction};
+use super::{Scene, Switch};
+use crate::input;
+
+const RESUME: usize = 0;
+const EXIT: usize = 1;
+
+// While this is on top of the stack the level below isn't updated, so the game stands still.
+pub struct PauseMenu {
+    menu: Menu,
+    action: Option<MenuAction>,
+    // Set when Exit is picked, the level checks it once it's back on top
+    exit: Rc<Cell<bool>>,
+    // The whole window, in pixels
+    screen: Rect,
+    square: Mesh,
+}
+
+impl PauseMenu {
+    pub fn new(ctx: &mut Context, exit: Rc<Cell<bool>>) -> Self {
+        let square = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(0.0, 0.0, 1.0, 1.0),
+            Color::new(1.0, 1.0, 1.0, 1.0)
+        ).unwrap();
+
+        let (w, h) = drawable_size(ctx);
+
+        PauseMenu {
+            menu: Menu::new(ctx, "Paused", vec!["Resume".to_string(), "Exit".to_string()]),
+            action: None,
+            exit,
+            screen: Rect::new(0.0, 0.0, w, h),
+            square,
+        }
+    }
+}
+
+impl Scene for PauseMenu {
+    fn update(&mut self, _ctx: &mut Context) -> Switch {
+        match self.action.take() {
+            Some(MenuAction::Pick(EXIT)) => {
+                self.exit.set(true);
+                Switch::Pop
+            }
+            // Quit closes the pause menu again, like it opened it
+            Some(MenuAction::Pick(RESUME)) | Some(MenuAction::Back) => Switch::Pop,
+            _ => Switch::None,
+        }
+    }
+
+    // Darken the level and draw the menu on top
+    fn draw(&mut self, ctx: &mut Context) -> ggez::GameResult<()> {
+        let previous = screen_coordinates(ctx);
+        set_screen_coordinates(ctx, self.screen)?;
+        draw(
+            ctx,
+            &self.square,
+            DrawParam::default()
+                .dest(Point2::new(self.screen.x, self.screen.y))
+                .scale(Vector2::new(self.screen.w, self.screen.h))
+                .color(Color::new(0.0, 0.0, 0.0, 0.6))
+        )?;
+        set_screen_coordinates(ctx, previous)?;
+
+        self.menu.draw(ctx)
+    }
+
+    fn input(&mut self, ev: input::Event, started: bool) {
+        if let Some(action) = self.menu.input(ev, started) {
+            self.action = Some(action);
+        }
+    }
+
+    fn mouse_motion(&mut self, x: f32, y: f32) {
+        self.menu.mouse_motion(x, y);
+    }
+
+    fn resize(&mut self, width: f32, height: f32) {
+        self.screen = Rect::new(0.0, 0.0, width, height);
+        self.menu.resize(width, height);
+    }
+
+    fn name(&self) -> &str {
+        "Pause menu"
+    }
+
+    fn draw_previous(&self) -> bool {
+        true
+    }
+}
@@ -6,10 +6,8 @@ pub const SMILEY: usize = 0;
 
 // We load all the sprites into ggez and return a reference to all of them
 pub fn load_sprites(ctx: &mut ggez::Context) -> Vec<Image> {
-    let mut sprites = Vec::new();
-
-    // 0
-    sprites.push(Image::new(ctx, "/smiley.png").unwrap());
-
-    sprites
+    vec![
+        // 0
+        Image::new(ctx, "/smiley.png").unwrap(),
+    ]
 }
\ No newline at end of file
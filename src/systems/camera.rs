@@ -0,0 +1,72 @@
+use crate::resources::*;
+use crate::input;
+use crate::level::TILE_SIZE;
+use crate::pathfinding::NavGrid;
+
+use specs;
+use ggez_goodies::{Point2, Vector2};
+
+// Camera speed in screen pixels per second
+const PAN_SPEED: f32 = 500.0;
+
+// How close to the edge of the window the mouse has to be to scroll
+const EDGE_SCROLL_MARGIN: f32 = 10.0;
+
+// How much the camera zooms in per second with the zoom axis all the way in
+const ZOOM_SPEED: f32 = 4.0;
+
+// Moves the camera with the keyboard, by holding the mouse against the edge of the window,
+// and zooms it with the mouse wheel.
+pub struct CameraSystem;
+
+impl<'a> specs::System<'a> for CameraSystem {
+    type SystemData = (
+        specs::Write<'a, Camera>,
+        specs::Read<'a, input::State>,
+        specs::Read<'a, NavGrid>,
+        specs::Read<'a, Clock>,
+    );
+
+    fn run(&mut self, (mut camera, input, nav_grid, clock): Self::SystemData) {
+        let mouse = input.mouse_position();
+
+        let edge = |near: bool| camera.edge_scroll && near;
+
+        let mut direction = Vector2::new(input.get_axis(input::Axis::PanX), input.get_axis(input::Axis::PanY));
+        if edge(mouse.x < EDGE_SCROLL_MARGIN) {
+            direction.x -= 1.0;
+        }
+        if edge(mouse.x > camera.screen_size.x - EDGE_SCROLL_MARGIN) {
+            direction.x += 1.0;
+        }
+        if edge(mouse.y < EDGE_SCROLL_MARGIN) {
+            direction.y -= 1.0;
+        }
+        if edge(mouse.y > camera.screen_size.y - EDGE_SCROLL_MARGIN) {
+            direction.y += 1.0;
+        }
+
+        // Going diagonally shouldn't be faster
+        if direction.length() > 1.0 {
+            direction = direction.normalize();
+        }
+        if direction != Vector2::zero() {
+            camera.pan(direction * PAN_SPEED * clock.dt as f32);
+        }
+
+        let zoom = input.get_axis(input::Axis::Zoom);
+        if zoom != 0.0 {
+            camera.zoom_at(mouse, ZOOM_SPEED.powf(zoom * clock.dt as f32));
+        }
+
+        // Don't let the middle of the screen leave the level
+        let center = camera.center();
+        let (min_x, min_y) = (-TILE_SIZE / 2.0, -TILE_SIZE / 2.0);
+        let max_x = (nav_grid.width() as f32 * TILE_SIZE - TILE_SIZE / 2.0).max(min_x);
+        let max_y = (nav_grid.height() as f32 * TILE_SIZE - TILE_SIZE / 2.0).max(min_y);
+        camera.center_on(Point2::new(
+            center.x.clamp(min_x, max_x),
+            center.y.clamp(min_y, max_y),
+        ));
+    }
+}
@@ -1,46 +1,56 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
 use crate::components::*;
 use crate::physics::*;
+use crate::resources::{Clock, CollisionEvent, CollisionEvents, Contact};
 
 use specs::{self, Join, World};
-use collider::{Collider, HbEvent, HbProfile, HbVel, geom::v2};
+use ggez_goodies::Vector2;
+use collider::{HbEvent, HbId, HbProfile, HbVel, geom::v2};
 
-// Length of one tick
-const TICK: f64 = 1.0/60.0;
-pub struct CollisionSystem {
-    tick: u64,
-}
-
-impl CollisionSystem {
-    pub fn new() -> Self {
-        CollisionSystem {
-            tick: 0,
-        }
-    }
-}
+pub struct CollisionSystem;
 
 impl<'a> specs::System<'a> for CollisionSystem {
     type SystemData = (
+        specs::Entities<'a>,
+        specs::Read<'a, Clock>,
         specs::Write<'a, Option<ColliderRes>>,
         specs::WriteStorage<'a, Position>,
         specs::WriteStorage<'a, Motion>,
         specs::ReadStorage<'a, BoxCollider>,
-        specs::ReadStorage<'a, Friendly>,
+        specs::ReadStorage<'a, ColliderShape>,
+        specs::ReadStorage<'a, Unit>,
+        specs::ReadStorage<'a, Mass>,
+        specs::Write<'a, CollisionEvents>,
+        specs::WriteExpect<'a, HitboxRemovals>,
     );
 
-    fn run(&mut self, (mut collider, mut pos, mut motion, box_collider, _friendly): Self::SystemData) {
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, clock, mut collider, mut pos, mut motion, box_collider, shape, unit, mass, mut events, mut removals) = data;
         let collider = collider.as_mut().unwrap();
 
+        // How easily everything is pushed around
+        let weights: HashMap<HbId, f64> = (&box_collider, mass.maybe(), unit.maybe()).join()
+            .map(|(box_collider, mass, unit)| {
+                let holding = unit.is_some_and(|unit| matches!(unit.action, Action::Hold));
+                let mass = mass.copied().unwrap_or_default();
+                (box_collider.id(), push_weight(mass.0, holding))
+            })
+            .collect();
+        let weight = |profile: &Profile| weights.get(&profile.id()).copied().unwrap_or(1.0);
+
+        // Everything that starts or stops touching during the tick, even if only for a moment
+        let mut contacts: Contacts = Vec::new();
+
         // Update motion from specs to collider world
         for (motion, box_collider) in (&motion, &box_collider).join() {
             let vel = HbVel::moving(v2(motion.velocity.x as f64, motion.velocity.y as f64));
 
-            collider.set_hitbox_vel(box_collider.0, vel);
+            collider.set_hitbox_vel(box_collider.id(), vel);
         }
 
-        self.tick += 1;
-        let end_time = self.tick as f64 * TICK;
+        // The clock is only advanced once every system has run
+        let end_time = clock.time() + clock.dt;
 
         // Advance simulation to end of tick
         while collider.time() < end_time {
@@ -50,16 +60,25 @@ impl<'a> specs::System<'a> for CollisionSystem {
 
             // Collect collisions if there are any.
             let mut collisions: VecDeque<(Profile, Profile)> = VecDeque::new();
-            for (e, profile_1, profile_2) in collider.next() {
-                if e == HbEvent::Collide {
+            match collider.next() {
+                Some((HbEvent::Collide, profile_1, profile_2)) => {
+                    contacts.push((Contact::Enter, profile_1, profile_2));
                     collisions.push_back((profile_1, profile_2));
                 }
+                Some((HbEvent::Separate, profile_1, profile_2)) => {
+                    contacts.push((Contact::Exit, profile_1, profile_2));
+                }
+                None => (),
             }
 
             // Handle all collisions
             while let Some((profile_1, profile_2)) = collisions.pop_front() {
+                // Triggers notice what touches them but don't push it
+                if !profile_1.is_solid() || !profile_2.is_solid() {
+                    continue;
+                }
                 // Skip wall to wall collisions
-                if profile_1.wall && profile_2.wall {
+                if profile_1.is_wall() && profile_2.is_wall() {
                     continue;
                 }
                 // Skip if collision is no longer relevant
@@ -67,14 +86,14 @@ impl<'a> specs::System<'a> for CollisionSystem {
                     continue;
                 }
 
-                if profile_1.wall {
-                    let new_collisions = handle_wall_collision(profile_2, profile_1, collider);
+                if profile_1.is_wall() {
+                    let new_collisions = handle_wall_collision(profile_2, profile_1, collider, &mut contacts);
 
                     for other in new_collisions {
                         collisions.push_back((profile_2, other));
                     }
-                } else if profile_2.wall {
-                    let new_collisions = handle_wall_collision(profile_1, profile_2, collider);
+                } else if profile_2.is_wall() {
+                    let new_collisions = handle_wall_collision(profile_1, profile_2, collider, &mut contacts);
 
                     for other in new_collisions {
                         collisions.push_back((profile_1, other));
@@ -83,14 +102,10 @@ impl<'a> specs::System<'a> for CollisionSystem {
                     let hb_1 = collider.get_hitbox(profile_1.id());
                     let hb_2 = collider.get_hitbox(profile_2.id());
 
-                    let _ = collider.remove_hitbox(profile_1.id());
-                    let _ = collider.remove_hitbox(profile_2.id());
+                    let (hb_1, hb_2) = resolve_entity_collision(hb_1, hb_2, weight(&profile_1), weight(&profile_2));
 
-                    let (hb_1, hb_2) = resolve_entity_collision(hb_1, hb_2);
+                    replace_hitboxes(collider, vec![(profile_1, hb_1), (profile_2, hb_2)], &mut contacts);
 
-                    collider.add_hitbox(profile_1, hb_1);
-                    collider.add_hitbox(profile_2, hb_2);
-                    
                     let new_collisions = collider.get_overlaps(profile_1.id());
                     
                     for other in new_collisions {
@@ -106,16 +121,28 @@ impl<'a> specs::System<'a> for CollisionSystem {
             }
         }
 
+        // Entities that were despawned or lost their hitbox since the last tick left what they touched
+        events.events = removals.take_exits();
+        events.events.extend(changed_contacts(contacts).into_iter()
+            .map(|(contact, profile_1, profile_2)| CollisionEvent {
+                contact,
+                first: entities.entity(profile_1.id),
+                second: entities.entity(profile_2.id),
+            }));
+
         // Update objects
-        for (mut pos, mut motion, box_collider) in (&mut pos, (&mut motion).maybe(), &box_collider).join() {
-            let hb = collider.get_hitbox(box_collider.0);
+        for (pos, motion, box_collider, shape) in (&mut pos, (&mut motion).maybe(), &box_collider, shape.maybe()).join() {
+            let hb = collider.get_hitbox(box_collider.id());
 
-            // Update pos
+            // Update pos, the hitbox can be off the middle of the entity
             let v = hb.value.pos;
-            pos.0.x = v.x as f32;
-            pos.0.y = v.y as f32;
+            let offset = shape.map(|shape| shape.offset()).unwrap_or_else(Vector2::zero);
+            pos.0.x = v.x as f32 - offset.x;
+            pos.0.y = v.y as f32 - offset.y;
 
-            if let Some(motion) = &mut motion {
+            // Only things that were moving by themselves keep moving. A push only lasts while it's
+            // happening, otherwise units that were shoved aside would drift off forever.
+            if let Some(motion) = motion.filter(|motion| motion.velocity.length() > 0.0) {
                 let v = hb.vel.value;
                 motion.velocity.x = v.x as f32;
                 motion.velocity.y = v.y as f32;
@@ -124,9 +151,10 @@ impl<'a> specs::System<'a> for CollisionSystem {
     }
 
     fn setup(&mut self, world: &mut World) {
-        // Creates a new empty collider world
-        let collider: ColliderRes = Collider::new();
+        // Creates a new empty collider world, and starts tracking removed hitboxes
+        setup_collider(world);
 
-        world.insert(Some(collider));
+        // And the rest of the resources, like the default setup would
+        <Self::SystemData as specs::SystemData>::setup(world);
     }
 }
@@ -0,0 +1,118 @@
+use crate::components::*;
+use crate::pathfinding::NavGrid;
+use crate::resources::{Clock, Random, TICKS_PER_SECOND};
+
+use rand::Rng;
+use specs::{self, Join};
+use ggez_goodies::Vector2;
+
+// Walks attacking friendlies up to their target and hits it whenever the weapon is ready.
+pub struct CombatSystem;
+
+impl<'a> specs::System<'a> for CombatSystem {
+    type SystemData = (
+        specs::Entities<'a>,
+        specs::ReadStorage<'a, Position>,
+        specs::WriteStorage<'a, Motion>,
+        specs::WriteStorage<'a, Unit>,
+        specs::WriteStorage<'a, Weapon>,
+        specs::WriteStorage<'a, Health>,
+        specs::Read<'a, NavGrid>,
+        specs::Read<'a, Clock>,
+        specs::Write<'a, Random>,
+    );
+
+    fn run(&mut self, (entities, pos, mut motion, mut unit, mut weapon, mut health, nav_grid, clock, mut random): Self::SystemData) {
+        for weapon in (&mut weapon).join() {
+            weapon.ready_in = (weapon.ready_in - clock.dt as f32).max(0.0);
+        }
+
+        for (own_pos, motion, unit, weapon) in (&pos, &mut motion, &mut unit, &mut weapon).join() {
+            let target = match unit.action {
+                Action::Attack { target, .. } => target,
+                _ => continue,
+            };
+
+            // Stop if the target is gone
+            let target_pos = match (entities.is_alive(target), pos.get(target), health.get(target)) {
+                (true, Some(target_pos), Some(target_health)) if !target_health.is_dead() => target_pos.0,
+                _ => {
+                    unit.action = Action::Standby;
+                    motion.velocity = Vector2::zero();
+                    continue;
+                }
+            };
+
+            let vector = target_pos - own_pos.0;
+
+            // Walk closer if we're out of range, around walls if there are any in the way.
+            // Finding the way is slow, so it is only done again once the target has moved to another cell,
+            // or every so often in case the way there has changed.
+            if vector.length() > weapon.range {
+                if let Action::Attack { waypoints, target_cell, repath_in, .. } = &mut unit.action {
+                    pub const REPATH_TICKS: u32 = TICKS_PER_SECOND;
+                    let cell = nav_grid.cell_at(target_pos);
+                    if *repath_in == 0 || cell != *target_cell {
+                        *waypoints = nav_grid.find_path(own_pos.0, target_pos).unwrap_or_default();
+                        *target_cell = cell;
+                        *repath_in = REPATH_TICKS;
+                    } else {
+                        *repath_in -= 1;
+                    }
+
+                    // How close we have to get to a waypoint before moving on to the next one.
+                    pub const DISTANCE_BEFORE_NEXT: f32 = 8.0;
+                    while waypoints.len() > 1 && (waypoints[0] - own_pos.0).length() < DISTANCE_BEFORE_NEXT {
+                        waypoints.remove(0);
+                    }
+
+                    // The last waypoint is where the target was, head for where it is now instead
+                    let direction = match waypoints.as_slice() {
+                        [next, _, ..] => *next - own_pos.0,
+                        _ => vector,
+                    };
+                    let direction = if direction.length() > 0.0 { direction } else { vector };
+
+                    motion.velocity = direction.normalize() * WALK_SPEED * nav_grid.speed_at(own_pos.0);
+                }
+                continue;
+            }
+
+            motion.velocity = Vector2::zero();
+
+            if weapon.ready_in <= 0.0 {
+                if let Some(target_health) = health.get_mut(target) {
+                    let spread = random.gen_range(-DAMAGE_SPREAD, DAMAGE_SPREAD);
+                    target_health.current -= weapon.damage * (1.0 + spread);
+                }
+                weapon.ready_in = weapon.cooldown;
+            }
+        }
+    }
+}
+
+// Deletes every entity that has run out of health.
+// The collider component is removed right away, so the despawn system takes the hitbox out before
+// the systems running later in the same tick can bump into it.
+pub struct DeathSystem;
+
+impl<'a> specs::System<'a> for DeathSystem {
+    type SystemData = (
+        specs::Entities<'a>,
+        specs::ReadStorage<'a, Health>,
+        specs::WriteStorage<'a, BoxCollider>,
+    );
+
+    fn run(&mut self, (entities, health, mut box_collider): Self::SystemData) {
+        for (entity, health) in (&entities, &health).join() {
+            if !health.is_dead() {
+                continue;
+            }
+
+            box_collider.remove(entity);
+
+            // The entity is only actually removed when the world is maintained
+            let _ = entities.delete(entity);
+        }
+    }
+}
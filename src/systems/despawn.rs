@@ -0,0 +1,21 @@
+use crate::components::*;
+use crate::physics::*;
+
+use specs;
+
+// Takes the hitboxes of deleted entities, and of entities that lost their BoxCollider, out of the
+// collider. Entities can be deleted anywhere, this makes sure nothing collides with them afterwards
+// and their index can be handed out again safely.
+pub struct DespawnSystem;
+
+impl<'a> specs::System<'a> for DespawnSystem {
+    type SystemData = (
+        specs::Write<'a, Option<ColliderRes>>,
+        specs::ReadStorage<'a, BoxCollider>,
+        specs::WriteExpect<'a, HitboxRemovals>,
+    );
+
+    fn run(&mut self, (mut collider, box_collider, mut removals): Self::SystemData) {
+        remove_stale_hitboxes(collider.as_mut().unwrap(), &box_collider, &mut removals);
+    }
+}
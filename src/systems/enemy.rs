@@ -0,0 +1,60 @@
+use crate::components::*;
+use crate::physics::{ColliderRes, Layers};
+use crate::query::SpatialQuery;
+
+use specs::{self, Join};
+use ggez_goodies::Vector2;
+
+// Makes idle enemies go after the closest player unit they can see,
+// and give up once it gets out of sight.
+pub struct EnemyAiSystem;
+
+impl<'a> specs::System<'a> for EnemyAiSystem {
+    type SystemData = (
+        specs::Entities<'a>,
+        specs::ReadStorage<'a, Position>,
+        specs::WriteStorage<'a, Motion>,
+        specs::WriteStorage<'a, Unit>,
+        specs::WriteStorage<'a, EnemyAi>,
+        specs::ReadStorage<'a, Team>,
+        specs::ReadStorage<'a, Health>,
+        specs::Read<'a, Option<ColliderRes>>,
+    );
+
+    fn run(&mut self, (entities, pos, mut motion, mut unit, mut ai, team, health, collider): Self::SystemData) {
+        let query = SpatialQuery::new(collider.as_ref().unwrap(), &entities);
+
+        for (own_pos, motion, unit, ai) in (&pos, &mut motion, &mut unit, &mut ai).join() {
+            match ai.state {
+                AiState::Idle => {
+                    // Look for the closest player unit in sight
+                    let target = query.nearest(own_pos.0, ai.sight_radius, Layers::GROUND | Layers::AIR, |entity| {
+                        team.get(entity) == Some(&Team::Player) && health.get(entity).is_some_and(|health| !health.is_dead())
+                    });
+
+                    if let Some(target) = target {
+                        ai.state = AiState::Chase(target);
+                        unit.action = Action::attack(target);
+                    }
+                }
+                AiState::Chase(target) => {
+                    let in_sight = entities.is_alive(target) && pos.get(target)
+                        .map(|target_pos| (target_pos.0 - own_pos.0).length() <= ai.sight_radius)
+                        .unwrap_or(false);
+
+                    // The combat system drops the attack once the target dies
+                    let attacking = match unit.action {
+                        Action::Attack { target: entity, .. } => entity == target,
+                        _ => false,
+                    };
+
+                    if !in_sight || !attacking {
+                        ai.state = AiState::Idle;
+                        unit.action = Action::Standby;
+                        motion.velocity = Vector2::zero();
+                    }
+                }
+            }
+        }
+    }
+}
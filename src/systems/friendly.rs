@@ -1,89 +1,208 @@
 use crate::components::*;
 use crate::resources::*;
 use crate::input;
+use crate::pathfinding::NavGrid;
+use crate::physics::*;
+use crate::query::SpatialQuery;
+use crate::formation::{self, Formation};
 
 use specs::{self, Join};
-use ggez_goodies::Vector2;
+use ggez::event::KeyMods;
+use ggez_goodies::{Point2, Vector2};
+
+// A unit under the point that passes the filter, if any.
+fn unit_at<F>(point: Point2, collider: &ColliderRes, entities: &specs::world::EntitiesRes, filter: F) -> Option<specs::Entity>
+where
+    F: Fn(specs::Entity) -> bool,
+{
+    SpatialQuery::new(collider, entities)
+        .at_point(point, Layers::GROUND | Layers::AIR)
+        .into_iter()
+        .find(|&entity| filter(entity))
+}
 
 pub struct FriendlySystem;
 
 impl<'a> specs::System<'a> for FriendlySystem {
     type SystemData = (
+        specs::Entities<'a>,
         specs::ReadStorage<'a, Position>,
         specs::WriteStorage<'a, Motion>,
-        specs::WriteStorage<'a, Friendly>,
+        specs::WriteStorage<'a, Unit>,
         specs::Read<'a, input::State>,
+        specs::Read<'a, Camera>,
         specs::Write<'a, Option<SelectionBox>>,
+        specs::Read<'a, NavGrid>,
+        specs::Read<'a, Option<ColliderRes>>,
+        specs::ReadStorage<'a, Health>,
+        specs::ReadStorage<'a, Team>,
+        specs::Write<'a, Formation>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
+            entities,
             pos,
             mut motion,
-            mut friendly,
+            mut unit,
             input,
-            mut selection_box
+            camera,
+            mut selection_box,
+            nav_grid,
+            collider,
+            health,
+            team,
+            mut formation,
         ) = data;
 
+        // Where the mouse is pointing in the world
+        let mouse_position = camera.screen_to_world(input.mouse_position());
+
         // Selection box
         if input.get_button_pressed(input::Button::Left) {
             let selection_box = selection_box.get_or_insert(SelectionBox::new());
-            selection_box.start = input.mouse_position()
+            selection_box.start = mouse_position
         }
 
         if input.get_button_down(input::Button::Left) {
             let selection_box = selection_box.get_or_insert(SelectionBox::new());
-            selection_box.stop = input.mouse_position()
+            selection_box.stop = mouse_position
         }
 
         if input.get_button_released(input::Button::Left) {
-            if let Some(sel_box) = &*selection_box {   
-                // Order x and y positions.
-                let x1 = sel_box.start.x.min(sel_box.stop.x);
-                let x2 = sel_box.start.x.max(sel_box.stop.x);
-                let y1 = sel_box.start.y.min(sel_box.stop.y);
-                let y2 = sel_box.start.y.max(sel_box.stop.y);
-                
-                for (pos, friendly) in (&pos, &mut friendly).join() {
-                    // If in bounding box, select
-                    friendly.selected = pos.0.x > x1 && pos.0.x < x2 && pos.0.y > y1 && pos.0.y < y2;
+            // A release can arrive without a press, if the press went to the pause menu
+            if let Some(sel_box) = selection_box.take() {
+                let modifiers = input.modifiers();
+
+                // A box smaller than this on screen is a click on whatever is under the mouse
+                pub const CLICK_DISTANCE: f32 = 4.0;
+                let picked: Vec<specs::Entity> = if (sel_box.stop - sel_box.start).length() * camera.zoom < CLICK_DISTANCE {
+                    unit_at(sel_box.stop, collider.as_ref().unwrap(), &entities, |entity| {
+                        unit.contains(entity) && team.get(entity) == Some(&Team::Player)
+                    })
+                        .into_iter()
+                        .collect()
+                } else {
+                    // Order x and y positions.
+                    let x1 = sel_box.start.x.min(sel_box.stop.x);
+                    let x2 = sel_box.start.x.max(sel_box.stop.x);
+                    let y1 = sel_box.start.y.min(sel_box.stop.y);
+                    let y2 = sel_box.start.y.max(sel_box.stop.y);
+
+                    // Only our own units can be selected.
+                    (&entities, &pos, &unit, &team).join()
+                        .filter(|(_, pos, _, team)| {
+                            **team == Team::Player && pos.0.x > x1 && pos.0.x < x2 && pos.0.y > y1 && pos.0.y < y2
+                        })
+                        .map(|(entity, ..)| entity)
+                        .collect()
+                };
+
+                for (entity, unit) in (&entities, &mut unit).join() {
+                    let in_pick = picked.contains(&entity);
+
+                    // Ctrl flips what was picked, shift adds it, otherwise it replaces the selection
+                    unit.selected = if modifiers.contains(KeyMods::CTRL) {
+                        unit.selected != in_pick
+                    } else if modifiers.contains(KeyMods::SHIFT) {
+                        unit.selected || in_pick
+                    } else {
+                        in_pick
+                    };
                 }
-            } else {
-                // Maybe use log instead
-                panic!("Button released but no selection box?!");
             }
-
-            *selection_box = None;
         }
-        
-        // Make selected friendlies go to right click
+
+        // Make selected friendlies go to right click, or attack what was clicked on
         if input.get_button_pressed(input::Button::Right) {
-            for friendly in (&mut friendly).join() {
-                if friendly.selected {
-                    friendly.action = Action::Goto(input.mouse_position());
+            let target_pos = mouse_position;
+
+            // Find an enemy under the cursor.
+            let target = unit_at(target_pos, collider.as_ref().unwrap(), &entities, |entity| {
+                health.contains(entity) && team.get(entity) == Some(&Team::Enemy)
+            });
+
+            match target {
+                Some(target) => {
+                    for unit in (&mut unit).join().filter(|unit| unit.selected) {
+                        unit.action = Action::attack(target);
+                    }
+                }
+                None => {
+                    // Spread out around the target instead of all heading for the same spot
+                    let selected: Vec<(specs::Entity, Point2)> = (&entities, &pos, &unit).join()
+                        .filter(|(_, _, unit)| unit.selected)
+                        .map(|(entity, pos, _)| (entity, pos.0))
+                        .collect();
+
+                    for (entity, destination) in formation::arrange(*formation, &selected, target_pos) {
+                        let start = pos.get(entity).unwrap().0;
+
+                        // Walk around walls if we can. If the spot is inside a wall or can't be reached, go for
+                        // the target itself, and if there is no way there either just head straight for the spot.
+                        let waypoints = nav_grid.find_path(start, destination)
+                            .or_else(|| nav_grid.find_path(start, target_pos))
+                            .unwrap_or_else(|| vec![destination]);
+
+                        unit.get_mut(entity).unwrap().action = Action::goto(waypoints);
+                    }
                 }
             }
         }
 
+        if input.get_button_pressed(input::Button::Formation) {
+            *formation = formation.next();
+        }
+
+        if input.get_button_pressed(input::Button::Hold) {
+            for (motion, unit) in (&mut motion, &mut unit).join().filter(|(_, unit)| unit.selected) {
+                unit.action = Action::Hold;
+                motion.velocity = Vector2::zero();
+            }
+        }
+
         // TODO: I could optimize this code to only run when Action::Goto changes or collisions and stuff
-        for (pos, motion, friendly) in (&pos, &mut motion, &mut friendly).join() {
-            match friendly.action {
-                Action::Goto(target_pos) => {
-                    let vector = target_pos - pos.0;
-
-                    // If we're close enough, stop.
-                    pub const DISTANCE_BEFORE_STOP: f32 = 4.0;
-                    if vector.length() < DISTANCE_BEFORE_STOP {
-                        friendly.action = Action::Standby;
-                        motion.velocity = Vector2::zero();
-                        continue;
-                    }
+        for (pos, motion, unit) in (&pos, &mut motion, &mut unit).join() {
+            if let Action::Goto { waypoints, closest, stalled } = &mut unit.action {
+                // How close we have to get to a waypoint before moving on to the next one.
+                pub const DISTANCE_BEFORE_NEXT: f32 = 8.0;
+                while waypoints.len() > 1 && (waypoints[0] - pos.0).length() < DISTANCE_BEFORE_NEXT {
+                    waypoints.remove(0);
+                }
 
-                    let direction = vector.normalize();
+                let vector = match waypoints.first() {
+                    Some(next) => *next - pos.0,
+                    None => Vector2::zero(),
+                };
 
-                    motion.velocity = direction * WALK_SPEED;
+                // Keep track of whether we're still getting closer to the destination
+                pub const MIN_PROGRESS: f32 = 1.0;
+                if waypoints.len() <= 1 {
+                    if vector.length() < *closest - MIN_PROGRESS {
+                        *closest = vector.length();
+                        *stalled = 0;
+                    } else {
+                        *stalled += 1;
+                    }
                 }
-                _ => (),
+
+                // If we're close enough to the destination, stop.
+                pub const DISTANCE_BEFORE_STOP: f32 = 4.0;
+                // Also stop if we're within a formation spot of it but haven't got any closer for a while,
+                // because someone is standing in the way.
+                pub const DISTANCE_BEFORE_BLOCKED: f32 = formation::SPACING;
+                pub const TICKS_BEFORE_BLOCKED: u32 = TICKS_PER_SECOND / 2;
+                let blocked = vector.length() < DISTANCE_BEFORE_BLOCKED && *stalled >= TICKS_BEFORE_BLOCKED;
+                if waypoints.len() <= 1 && (vector.length() < DISTANCE_BEFORE_STOP || blocked) {
+                    unit.action = Action::Standby;
+                    motion.velocity = Vector2::zero();
+                    continue;
+                }
+
+                let direction = vector.normalize();
+
+                // Terrain like mud slows us down
+                motion.velocity = direction * WALK_SPEED * nav_grid.speed_at(pos.0);
             }
         }
     }
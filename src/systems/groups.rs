@@ -0,0 +1,88 @@
+use crate::components::*;
+use crate::resources::*;
+use crate::input;
+
+use specs::{self, Join};
+use ggez::event::KeyMods;
+use ggez_goodies::Vector2;
+
+// Recalling the same group again within this many ticks centres the camera on it
+const DOUBLE_TAP_TICKS: u64 = 20;
+
+// Stores the selection in a control group with ctrl and a number key, and selects
+// the group again with just the number key.
+pub struct ControlGroupSystem;
+
+impl<'a> specs::System<'a> for ControlGroupSystem {
+    type SystemData = (
+        specs::Entities<'a>,
+        specs::ReadStorage<'a, Position>,
+        specs::WriteStorage<'a, Unit>,
+        specs::Read<'a, input::State>,
+        specs::Read<'a, Clock>,
+        specs::Write<'a, Camera>,
+        specs::Write<'a, ControlGroups>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, pos, mut unit, input, clock, mut camera, mut control_groups) = data;
+
+        // Forget units that died or can't be ordered around anymore
+        for group in control_groups.groups.iter_mut() {
+            group.retain(|&entity| entities.is_alive(entity) && unit.contains(entity));
+        }
+
+        let pressed = input::Button::GROUPS.iter()
+            .position(|&button| input.get_button_pressed(button));
+        let index = match pressed {
+            Some(index) => index,
+            None => return,
+        };
+
+        if input.modifiers().contains(KeyMods::CTRL) {
+            let selected: Vec<specs::Entity> = (&entities, &unit).join()
+                .filter(|(_, unit)| unit.selected)
+                .map(|(entity, _)| entity)
+                .collect();
+
+            // Storing an empty selection would only throw the group away
+            if !selected.is_empty() {
+                control_groups.groups[index] = selected;
+            }
+            control_groups.last_recall = None;
+            return;
+        }
+
+        let group = &control_groups.groups[index];
+        if group.is_empty() {
+            return;
+        }
+
+        for (entity, unit) in (&entities, &mut unit).join() {
+            unit.selected = group.contains(&entity);
+        }
+
+        // Centre on the middle of the group when its key is pressed twice in a row
+        let double_tap = matches!(
+            control_groups.last_recall,
+            Some((last, tick)) if last == index && clock.tick - tick <= DOUBLE_TAP_TICKS
+        );
+        if double_tap {
+            let positions: Vec<Vector2> = group.iter()
+                .filter_map(|&entity| pos.get(entity))
+                .map(|pos| pos.0.to_vector())
+                .collect();
+
+            if !positions.is_empty() {
+                let sum = positions.iter().fold(Vector2::zero(), |sum, pos| sum + *pos);
+                camera.center_on((sum / positions.len() as f32).to_point());
+            }
+        }
+
+        control_groups.last_recall = match double_tap {
+            // A third tap starts over instead of centring again
+            true => None,
+            false => Some((index, clock.tick)),
+        };
+    }
+}
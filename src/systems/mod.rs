@@ -3,6 +3,12 @@ use crate::components::*;
 use specs::{self, Join};
 
 mod friendly;
+mod combat;
+mod enemy;
+mod camera;
+mod groups;
+mod steering;
+mod despawn;
 pub mod collision;
 
 pub struct MovementSystem;
@@ -27,7 +33,14 @@ impl<'a> specs::System<'a> for MovementSystem {
 pub fn register_systems() -> specs::Dispatcher<'static, 'static> {
     specs::DispatcherBuilder::new()
         //.with(MovementSystem, "movement", &[])
-        .with(friendly::FriendlySystem, "friendly", &[])
-        .with(collision::CollisionSystem::new(), "collision", &["friendly"])
+        .with(groups::ControlGroupSystem, "groups", &[])
+        .with(camera::CameraSystem, "camera", &["groups"])
+        .with(friendly::FriendlySystem, "friendly", &["camera", "groups"])
+        .with(enemy::EnemyAiSystem, "enemy_ai", &["friendly"])
+        .with(combat::CombatSystem, "combat", &["friendly", "enemy_ai"])
+        .with(combat::DeathSystem, "death", &["combat"])
+        .with(despawn::DespawnSystem, "despawn", &["death"])
+        .with(steering::SteeringSystem, "steering", &["friendly", "enemy_ai", "combat", "despawn"])
+        .with(collision::CollisionSystem, "collision", &["steering"])
         .build()
 }
\ No newline at end of file
@@ -0,0 +1,168 @@
+use crate::components::*;
+use crate::physics::*;
+use crate::query::SpatialQuery;
+
+use specs::{self, Join};
+use ggez_goodies::{Point2, Vector2};
+use collider::{HbId, HbProfile, geom::*};
+
+// Units try to keep at least this far between their middles. It's less than the formation
+// spacing, so units standing in formation don't push each other out of it.
+const SEPARATION_RADIUS: f32 = 26.0;
+const SEPARATION_WEIGHT: f32 = 1.5;
+
+// Units slow down once they're this close to where they're going
+const ARRIVAL_RADIUS: f32 = 40.0;
+// ... but never below this part of their speed, so they do get there
+const MIN_ARRIVAL_SPEED: f32 = 0.25;
+
+// How far ahead units look for someone standing in their way, in seconds of walking
+const LOOKAHEAD_TIME: f32 = 0.4;
+const AVOIDANCE_WEIGHT: f32 = 1.0;
+
+// Units keep their middle this far from walls and water ahead of them. It's less than half a tile
+// plus a bit, so units walking along a wall in the middle of the tiles next to it aren't bothered.
+const WALL_CLEARANCE: f32 = 14.0;
+const WALL_WEIGHT: f32 = 1.5;
+
+// Bends the velocity the other systems picked, so moving units keep out of each other's way
+// and out of walls before they bump into them. Only units that are already moving are steered,
+// the speed they were given is kept and only the direction changes, except when slowing down on arrival.
+pub struct SteeringSystem;
+
+impl<'a> specs::System<'a> for SteeringSystem {
+    type SystemData = (
+        specs::Entities<'a>,
+        specs::ReadStorage<'a, Position>,
+        specs::WriteStorage<'a, Motion>,
+        specs::ReadStorage<'a, Unit>,
+        specs::ReadStorage<'a, BoxCollider>,
+        specs::Read<'a, Option<ColliderRes>>,
+    );
+
+    fn run(&mut self, (entities, pos, mut motion, unit, box_collider, collider): Self::SystemData) {
+        let collider = collider.as_ref().unwrap();
+        let query = SpatialQuery::new(collider, &entities);
+
+        // Other units in the area that the unit would bump into, and where they are
+        let units_near = |own: &Profile, at: Point2, diameter: f32| -> Vec<(specs::Entity, Point2)> {
+            let area = Shape::circle(diameter as f64).place(v2(at.x as f64, at.y as f64));
+            let query_profile = Profile::query(Layers::GROUND | Layers::AIR);
+
+            let mut near: Vec<(specs::Entity, Point2)> = collider.query_overlaps(&area, &query_profile)
+                .into_iter()
+                .filter(|profile| profile.id() != own.id() && own.can_interact(profile))
+                .map(|profile| entities.entity(profile.id))
+                .filter_map(|other| pos.get(other).map(|pos| (other, pos.0)))
+                .collect();
+
+            // The collider returns them in whatever order they were added in, which changes when a
+            // game is loaded. Adding up floats in a different order gives a slightly different result.
+            near.sort_by_key(|(other, _)| other.id());
+            near
+        };
+        let is_moving = |entity: specs::Entity| motion.get(entity)
+            .is_some_and(|motion| motion.velocity.length() > 0.0);
+
+        let mut steered = Vec::new();
+        for (entity, own_pos, own_motion, box_collider) in (&entities, &pos, &motion, &box_collider).join() {
+            let speed = own_motion.velocity.length();
+            if speed == 0.0 {
+                continue;
+            }
+            let forward = own_motion.velocity / speed;
+            let mut direction = forward;
+
+            // Separation: push away from everyone too close, harder the closer they are
+            for (_, other_pos) in units_near(&box_collider.0, own_pos.0, SEPARATION_RADIUS * 2.0) {
+                let away = own_pos.0 - other_pos;
+                let distance = away.length();
+                if distance > 0.0 && distance < SEPARATION_RADIUS {
+                    direction += away / distance * (1.0 - distance / SEPARATION_RADIUS) * SEPARATION_WEIGHT;
+                }
+            }
+
+            // Obstacle avoidance: step to the side of anyone standing still just ahead
+            let ahead = own_pos.0 + forward * speed * LOOKAHEAD_TIME;
+            let right = Vector2::new(-forward.y, forward.x);
+            for (other, other_pos) in units_near(&box_collider.0, ahead, SEPARATION_RADIUS) {
+                if is_moving(other) {
+                    continue;
+                }
+
+                // Go around on whichever side we're already on, to the right if it's dead ahead
+                let side = (ahead - other_pos).dot(right);
+                let side = if side < 0.0 { -1.0 } else { 1.0 };
+                direction += right * side * AVOIDANCE_WEIGHT;
+            }
+
+            // Wall avoidance: turn away from walls and water just ahead, for units that can't go over them.
+            // We don't look past the next waypoint, paths lead right up to walls before turning.
+            if box_collider.0.mask.intersects(Layers::WALL | Layers::WATER) {
+                let lookahead = match unit.get(entity).and_then(|unit| next_waypoint(&unit.action)) {
+                    Some(next) => (next - own_pos.0).length().min(speed * LOOKAHEAD_TIME),
+                    None => speed * LOOKAHEAD_TIME,
+                };
+                let ahead = own_pos.0 + forward * lookahead;
+                for wall in query.in_circle(ahead, WALL_CLEARANCE, Layers::WALL | Layers::WATER) {
+                    let away = ahead - closest_point(&collider.get_hitbox(wall.id() as HbId).value, ahead);
+                    let distance = away.length();
+                    if distance > 0.0 && distance < WALL_CLEARANCE {
+                        direction += away / distance * (1.0 - distance / WALL_CLEARANCE) * WALL_WEIGHT;
+                    }
+                }
+            }
+
+            // Arrival: slow down towards the end of a walk
+            let mut speed = speed;
+            if let Some(Action::Goto { waypoints, .. }) = unit.get(entity).map(|unit| &unit.action) {
+                if let [destination] = waypoints[..] {
+                    let distance = (destination - own_pos.0).length();
+                    speed *= (distance / ARRIVAL_RADIUS).clamp(MIN_ARRIVAL_SPEED, 1.0);
+                }
+            }
+
+            // Never turn around completely, that only makes units dance on the spot
+            if direction.dot(forward) <= 0.0 {
+                direction = right * direction.dot(right).signum();
+            }
+
+            steered.push((entity, direction.normalize() * speed));
+        }
+
+        for (entity, velocity) in steered {
+            if let Some(motion) = motion.get_mut(entity) {
+                motion.velocity = velocity;
+            }
+        }
+    }
+}
+
+// Where the action is leading the unit next, if it's following waypoints
+fn next_waypoint(action: &Action) -> Option<Point2> {
+    match action {
+        Action::Goto { waypoints, .. } | Action::Attack { waypoints, .. } => waypoints.first().cloned(),
+        _ => None,
+    }
+}
+
+// The point on the edge of the shape closest to the point, or the point itself if it's inside
+fn closest_point(shape: &PlacedShape, point: Point2) -> Point2 {
+    let center = Point2::new(shape.pos.x as f32, shape.pos.y as f32);
+    let half = Vector2::new(shape.dims().x as f32, shape.dims().y as f32) / 2.0;
+
+    match shape.kind() {
+        ShapeKind::Circle => {
+            let offset = point - center;
+            if offset.length() <= half.x {
+                point
+            } else {
+                center + offset.normalize() * half.x
+            }
+        }
+        ShapeKind::Rect => Point2::new(
+            point.x.clamp(center.x - half.x, center.x + half.x),
+            point.y.clamp(center.y - half.y, center.y + half.y),
+        ),
+    }
+}
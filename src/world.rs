@@ -1,4 +1,6 @@
-use rand::prelude::*;
+use std::collections::VecDeque;
+use std::path::Path;
+
 use crate::{
     input,
     systems,
@@ -6,28 +8,81 @@ use crate::{
     resources,
     sprites,
     level,
-    physics
+    physics::{self, HitboxError},
+    level::LevelError,
+    replay::{Replay, ReplayEvent},
+    save::{self, SaveError},
 };
 
+use ggez::event::KeyMods;
 use ggez::graphics::*;
 use ggez_goodies::{Point2, Vector2};
 use specs::{self, world::Builder, WorldExt};
-use collider::geom::*;
 
-// The game world. Every entity lives in here.
-pub struct World {
+// Width and height of a character
+const UNIT_SIZE: f64 = 20.0;
+
+// The game simulation. Every entity lives in here.
+// It doesn't touch ggez at all, so it can be created and stepped without a window.
+pub struct Simulation {
     // ECS
     specs_world: specs::World, // Contains components and entities
     dispatcher: specs::Dispatcher<'static, 'static>, // Contains systems
 
-    // Meshes for rendering
-    sprites: Vec<Image>, // Sprites are loaded upen world initialization and
-                         // aren't supposed to change after that
-    square: Mesh, // Mesh for rendering rectangles
+    // Name of the level in resources/rooms
+    level: String,
+    // Everything that has come in from outside since recording started
+    recording: Option<Replay>,
+    // Events still to be played back, live input is ignored while there are any
+    playback: Option<VecDeque<(u64, ReplayEvent)>>,
 }
 
-impl World {
-    pub fn new(ctx: &mut ggez::Context) -> Self {
+impl Simulation {
+    // Create a simulation of the level with the given name in resources/rooms, with a random seed.
+    pub fn new<P: AsRef<Path>>(level: &P) -> Result<Self, LevelError> {
+        Self::with_seed(level, rand::random())
+    }
+
+    // Create a simulation of the level, drawing all its random numbers from the given seed.
+    // Colours that aren't in the level legend are ignored.
+    pub fn with_seed<P: AsRef<Path>>(level: &P, seed: u64) -> Result<Self, LevelError> {
+        Self::with_options(level, seed, false)
+    }
+
+    // Like with_seed, but in strict mode colours that aren't in the level legend are an error,
+    // so mistakes in the level image are caught early.
+    pub fn with_options<P: AsRef<Path>>(level: &P, seed: u64, strict: bool) -> Result<Self, LevelError> {
+        let mut simulation = Self::empty(&level.as_ref().to_string_lossy(), seed);
+
+        // Make a test entity.
+        simulation
+            .specs_world
+            .create_entity()
+            .with(components::Position(Point2::new(0.0, 0.0)))
+            .with(components::Motion {
+                velocity: Vector2::new(1.0, 1.0),
+            })
+            .with(components::Renderable::SpriteId(sprites::SMILEY))
+            .build();
+
+        // Put a unit on every spawn point painted in the level
+        let level = level::load_level(level, &mut simulation.specs_world, strict)?;
+
+        // Start out looking at the player
+        if let Some(spawn_point) = level.spawn_points.iter().find(|s| s.team == components::Team::Player) {
+            simulation.specs_world.fetch_mut::<resources::Camera>().center_on(spawn_point.pos);
+        }
+
+        for spawn_point in level.spawn_points {
+            let (x, y) = (spawn_point.pos.x as f64, spawn_point.pos.y as f64);
+            simulation.spawn_unit(x, y, spawn_point.team)?;
+        }
+
+        Ok(simulation)
+    }
+
+    // A simulation with nothing in it, with every system and resource set up.
+    pub(crate) fn empty(level: &str, seed: u64) -> Self {
         // Create empty specs world
         let mut specs_world = specs::WorldExt::new();
         components::register_components(&mut specs_world);
@@ -35,87 +90,315 @@ impl World {
         // Add input state
         specs_world.insert(input::State::new());
 
+        // Add the clock and the random number generator
+        specs_world.insert(resources::Clock::default());
+        specs_world.insert(resources::Random::new(seed));
+
         let mut dispatcher = systems::register_systems();
         dispatcher.setup(&mut specs_world);
 
-        // Add mesh for debug square rendering
-        let square = Mesh::new_rectangle(
-            ctx,
-            DrawMode::fill(),
-            Rect::new(0.0, 0.0, 1.0, 1.0),
-            Color::new(1.0, 1.0, 1.0, 1.0)
-        ).unwrap();
-
-        // Add images for sprite rendering
-        let sprites = sprites::load_sprites(ctx);
-
-        let mut the_world = Self {
-            // resources: store,
+        Self {
             specs_world,
             dispatcher,
-            square,
-            sprites,
+            level: level.to_string(),
+            recording: None,
+            playback: None,
+        }
+    }
+
+    // Continue a game saved with Simulation::save.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SaveError> {
+        save::load(path)
+    }
+
+    // Start the match a replay was recorded from, and play its input back.
+    pub fn from_replay(replay: Replay) -> Result<Self, LevelError> {
+        let mut simulation = Self::with_seed(&replay.level, replay.seed)?;
+        simulation.playback = Some(replay.events.into());
+
+        Ok(simulation)
+    }
+
+    // Create a character for the given team at the given position. Enemies get an AI to control them.
+    pub fn spawn_unit(&mut self, x: f64, y: f64, team: components::Team) -> Result<specs::Entity, HitboxError> {
+        let w: f64 = UNIT_SIZE;
+        let h: f64 = UNIT_SIZE;
+
+        let color = match team {
+            components::Team::Player => ggez::graphics::Color::new(0.0, 0.0, 1.0, 1.0),
+            components::Team::Enemy => ggez::graphics::Color::new(1.0, 0.0, 0.0, 1.0),
         };
 
-        // Make a test entity.
-        the_world
+        let entity = self
             .specs_world
             .create_entity()
-            .with(components::Position(Point2::new(0.0, 0.0)))
+            .with(components::Position(Point2::new(x as f32, y as f32)))
             .with(components::Motion {
-                velocity: Vector2::new(1.0, 1.0),
+                velocity: Vector2::new(0.0, 0.0),
             })
-            .with(components::Renderable::SpriteId(sprites::SMILEY))
+            .with(components::Renderable::Rectangle {
+                w: w as f32,
+                h: h as f32,
+                color,
+            })
+            // Round, not the square that is drawn: units pushing through a crowd slide off each other's
+            // round hitboxes, where flat sides meet and a unit gets wedged in for good
+            .with(components::ColliderShape::circle(w as f32))
+            .with(components::Unit::default())
+            .with(components::Health::new(100.0))
+            .with(components::Weapon::default())
+            .with(components::Mass::default())
+            .with(team)
             .build();
 
-        let mut rng = thread_rng();
-        for _ in 0..100 {
-            let x: f64 = rng.gen_range(0.0, 800.0);
-            let y: f64 = rng.gen_range(0.0, 600.0);
+        if team == components::Team::Enemy {
+            self.specs_world
+                .write_storage::<components::EnemyAi>()
+                .insert(entity, components::EnemyAi::default())
+                .unwrap();
+        }
 
-            let w: f64 = 20.0;
-            let h: f64 = 20.0;
+        physics::add_box_collider(entity, physics::Layers::GROUND, &mut self.specs_world)?;
 
-            let entity = the_world
-                .specs_world
-                .create_entity()
-                .with(components::Position(Point2::new(x as f32, y as f32)))
-                .with(components::Motion {
-                    velocity: Vector2::new(0.0, 0.0),
-                })
-                .with(components::Renderable::Rectangle {
-                    w: w as f32,
-                    h: h as f32,
-                    color: ggez::graphics::Color::new(0.0, 0.0, 1.0, 1.0),
-                })
-                .with(components::Friendly::default())
-                .build();
-            
-            let hitbox = Shape::circle(w).place(v2(x, y)).still();
-            physics::add_box_collider(entity, hitbox, false, &mut the_world.specs_world);
-        }
+        Ok(entity)
+    }
+
+    // Create an invisible area centred on the given position that doesn't stop anything, but shows up in
+    // the CollisionEvents resource whenever something walks in or out of it.
+    pub fn spawn_trigger(&mut self, x: f64, y: f64, w: f64, h: f64) -> Result<specs::Entity, HitboxError> {
+        let entity = self
+            .specs_world
+            .create_entity()
+            .with(components::Position(Point2::new(x as f32, y as f32)))
+            .with(components::ColliderShape::rect(w as f32, h as f32))
+            .build();
+
+        physics::add_box_collider(entity, physics::Layers::TRIGGER, &mut self.specs_world)?;
+
+        Ok(entity)
+    }
+
+    // Remove an entity from the game right away, along with its hitbox.
+    // Fails if the entity was already removed.
+    pub fn despawn(&mut self, entity: specs::Entity) -> Result<(), specs::error::WrongGeneration> {
+        self.specs_world.delete_entity(entity)?;
 
-        level::load_level(&"test.png", &mut the_world.specs_world);
+        // The collider is always there once the systems are set up
+        let _ = physics::sync_collider(&self.specs_world);
 
-        the_world
+        Ok(())
     }
 
-    pub fn update(&mut self, _ctx: &mut ggez::Context) {
+    // Advance the simulation by one tick.
+    pub fn update(&mut self) {
+        // Feed in the input recorded for this tick
+        if let Some(playback) = &mut self.playback {
+            let tick = self.specs_world.fetch::<resources::Clock>().tick;
+            let mut events = Vec::new();
+            while playback.front().is_some_and(|(event_tick, _)| *event_tick <= tick) {
+                events.extend(playback.pop_front().map(|(_, event)| event));
+            }
+            if playback.is_empty() {
+                self.playback = None;
+            }
+
+            for event in events {
+                self.apply_event(event);
+            }
+        }
+
         // Run systems
-        self.dispatcher.dispatch(&mut self.specs_world);
-        
+        self.dispatcher.dispatch(&self.specs_world);
+
+        // Actually remove entities deleted by the systems
+        self.specs_world.maintain();
+
+        self.specs_world.fetch_mut::<resources::Clock>().tick += 1;
+
         // Update input state
         // This has to be last. Order is important for get_button_pressed and _released.
-        self.specs_world.fetch_mut::<input::State>().update();
+        let dt = self.specs_world.fetch::<resources::Clock>().dt;
+        self.specs_world.fetch_mut::<input::State>().update(dt as f32);
+    }
+
+    pub fn handle_input(&mut self, ev: input::Event, started: bool) {
+        self.handle_event(ReplayEvent::Input(ev, started));
+    }
+
+    pub fn handle_mouse_motion(&mut self, x: f32, y: f32) {
+        self.handle_event(ReplayEvent::MouseMotion(x, y));
+    }
+
+    // Push an axis from an input that moves in steps, like the mouse wheel
+    pub fn handle_axis_impulse(&mut self, axis: input::Axis, amount: f32) {
+        self.handle_event(ReplayEvent::AxisImpulse(axis, amount));
+    }
+
+    pub fn handle_resize(&mut self, width: f32, height: f32) {
+        self.handle_event(ReplayEvent::Resize(width, height));
+    }
+
+    // Only changes are passed on, since the modifiers are sent along with every key and click
+    pub fn handle_modifiers(&mut self, modifiers: KeyMods) {
+        if self.specs_world.fetch::<input::State>().modifiers() != modifiers {
+            self.handle_event(ReplayEvent::Modifiers(modifiers));
+        }
+    }
+
+    // Live input. Recorded if we're recording, and dropped while a replay is playing.
+    fn handle_event(&mut self, event: ReplayEvent) {
+        if self.playback.is_some() {
+            return;
+        }
+
+        if let Some(recording) = &mut self.recording {
+            let tick = self.specs_world.fetch::<resources::Clock>().tick;
+            recording.record(tick, event);
+        }
+
+        self.apply_event(event);
+    }
+
+    fn apply_event(&mut self, event: ReplayEvent) {
+        match event {
+            ReplayEvent::Input(ev, started) => self.specs_world.fetch_mut::<input::State>()
+                .update_effect(ev, started),
+            ReplayEvent::MouseMotion(x, y) => self.specs_world.fetch_mut::<input::State>()
+                .update_mouse_position(x, y),
+            ReplayEvent::AxisImpulse(axis, amount) => self.specs_world.fetch_mut::<input::State>()
+                .update_axis_impulse(axis, amount),
+            ReplayEvent::Modifiers(modifiers) => self.specs_world.fetch_mut::<input::State>()
+                .update_modifiers(modifiers),
+            ReplayEvent::Resize(width, height) => {
+                // Keep looking at the same spot
+                let mut camera = self.specs_world.fetch_mut::<resources::Camera>();
+                let center = camera.center();
+                camera.screen_size = Vector2::new(width, height);
+                camera.center_on(center);
+            }
+        }
+    }
+
+    // Record all input from now on. Where the mouse is, how big the screen is and which modifiers
+    // are held are recorded first, since they were set before recording started.
+    pub fn start_recording(&mut self) {
+        let mut recording = Replay::new(&self.level, self.seed());
+        let tick = self.tick();
+
+        let (mouse, modifiers) = {
+            let input = self.specs_world.fetch::<input::State>();
+            (input.mouse_position(), input.modifiers())
+        };
+        let screen_size = self.specs_world.fetch::<resources::Camera>().screen_size;
+        recording.record(tick, ReplayEvent::Resize(screen_size.x, screen_size.y));
+        recording.record(tick, ReplayEvent::MouseMotion(mouse.x, mouse.y));
+        recording.record(tick, ReplayEvent::Modifiers(modifiers));
+
+        self.recording = Some(recording);
+    }
+
+    // Write everything in the simulation to a file, so it can be continued later with Simulation::load.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        save::save(self, path)
+    }
+
+    // What has been recorded so far
+    pub fn recording(&self) -> Option<&Replay> {
+        self.recording.as_ref()
+    }
+
+    // Whether a replay is still being played back
+    pub fn is_replaying(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    pub fn level(&self) -> &str {
+        &self.level
+    }
+
+    // Number of ticks run so far
+    pub fn tick(&self) -> u64 {
+        self.specs_world.fetch::<resources::Clock>().tick
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.specs_world.fetch::<resources::Random>().seed()
+    }
+
+    // The specs world, for inspecting components and resources.
+    pub fn specs_world(&self) -> &specs::World {
+        &self.specs_world
+    }
+
+    pub fn specs_world_mut(&mut self) -> &mut specs::World {
+        &mut self.specs_world
+    }
+}
+
+// The game world. The simulation together with everything needed to draw it.
+pub struct World {
+    simulation: Simulation,
+    // Draw the outlines of all hitboxes
+    show_hitboxes: bool,
+
+    // Meshes for rendering
+    sprites: Vec<Image>, // Sprites are loaded upen world initialization and
+                         // aren't supposed to change after that
+    square: Mesh, // Mesh for rendering rectangles
+}
+
+impl World {
+    pub fn new<P: AsRef<Path>>(ctx: &mut ggez::Context, level: &P) -> Result<Self, LevelError> {
+        Ok(Self::with_simulation(ctx, Simulation::new(level)?))
+    }
+
+    // Wrap an existing simulation with the render resources it needs.
+    pub fn with_simulation(ctx: &mut ggez::Context, mut simulation: Simulation) -> Self {
+        // Add mesh for debug square rendering
+        let square = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(0.0, 0.0, 1.0, 1.0),
+            Color::new(1.0, 1.0, 1.0, 1.0)
+        ).unwrap();
+
+        // Add images for sprite rendering
+        let sprites = sprites::load_sprites(ctx);
+
+        // Fit the camera to the window
+        let (w, h) = drawable_size(ctx);
+        simulation.handle_resize(w, h);
+        simulation.specs_world.fetch_mut::<resources::Camera>().edge_scroll = true;
+
+        // The mouse might already be inside the window before it first moves
+        let mouse = ggez::input::mouse::position(ctx);
+        simulation.handle_mouse_motion(mouse.x, mouse.y);
+
+        Self {
+            simulation,
+            show_hitboxes: false,
+            square,
+            sprites,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.simulation.update();
     }
 
     pub fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
         use components::{Renderable, Position};
         use specs::{Join, ReadStorage};
 
+        let specs_world = self.simulation.specs_world();
+
+        // Everything in the world is drawn through the camera
+        let screen = screen_coordinates(ctx);
+        set_screen_coordinates(ctx, specs_world.fetch::<resources::Camera>().view())?;
+
         // We can draw every entity that has both a position and a renderable component
-        let (renderable, position): (ReadStorage<Renderable>, ReadStorage<Position>) = self.specs_world.system_data();
-        
+        let (renderable, position): (ReadStorage<Renderable>, ReadStorage<Position>) = specs_world.system_data();
+
         // .join() to make sure we only get entities that have both
         for (renderable, position) in (&renderable, &position).join() {
             match renderable {
@@ -138,8 +421,12 @@ impl World {
             }
         }
 
+        if self.show_hitboxes {
+            self.draw_hitboxes(ctx)?;
+        }
+
         // Render selection box
-        let sel_box = self.specs_world.fetch::<Option<resources::SelectionBox>>();
+        let sel_box = specs_world.fetch::<Option<resources::SelectionBox>>();
         if let Some(sel_box) = &*sel_box {
             draw(
                 ctx,
@@ -154,16 +441,97 @@ impl World {
             )?
         }
 
+        set_screen_coordinates(ctx, screen)?;
+
         Ok(())
     }
 
+    // Outline every hitbox where the collider has it, coloured by the layer it's on.
+    fn draw_hitboxes(&self, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
+        use collider::geom::ShapeKind;
+        use specs::Join;
+
+        let specs_world = self.simulation.specs_world();
+        let collider = specs_world.fetch::<Option<physics::ColliderRes>>();
+        let collider = match collider.as_ref() {
+            Some(collider) => collider,
+            None => return Ok(()),
+        };
+
+        // Thin lines however far the camera is zoomed in
+        let line_width = 1.0 / specs_world.fetch::<resources::Camera>().zoom;
+
+        let mut mesh = MeshBuilder::new();
+        let mut empty = true;
+        for box_collider in specs_world.read_storage::<components::BoxCollider>().join() {
+            let color = match box_collider.0.layer {
+                layer if layer.intersects(physics::Layers::TRIGGER) => Color::new(0.2, 1.0, 0.2, 1.0),
+                layer if layer.intersects(physics::Layers::WALL) => Color::new(1.0, 1.0, 0.2, 1.0),
+                layer if layer.intersects(physics::Layers::WATER) => Color::new(0.2, 0.6, 1.0, 1.0),
+                _ => Color::new(1.0, 0.2, 0.2, 1.0),
+            };
+
+            let shape = collider.get_hitbox(box_collider.id()).value;
+            let (pos, dims) = (shape.pos, shape.dims());
+            match shape.kind() {
+                ShapeKind::Circle => {
+                    let center = Point2::new(pos.x as f32, pos.y as f32);
+                    mesh.circle(DrawMode::stroke(line_width), center, dims.x as f32 / 2.0, 0.1, color);
+                }
+                ShapeKind::Rect => {
+                    let rect = Rect::new(
+                        (pos.x - dims.x / 2.0) as f32,
+                        (pos.y - dims.y / 2.0) as f32,
+                        dims.x as f32,
+                        dims.y as f32,
+                    );
+                    mesh.rectangle(DrawMode::stroke(line_width), rect, color);
+                }
+            }
+            empty = false;
+        }
+
+        // A mesh can't be built without anything in it
+        if empty {
+            return Ok(());
+        }
+        let mesh = mesh.build(ctx)?;
+        draw(ctx, &mesh, DrawParam::default())
+    }
+
     pub fn handle_input(&mut self, ev: input::Event, started: bool) {
-        self.specs_world.fetch_mut::<input::State>()
-            .update_effect(ev, started);
+        use input::{Button, Event};
+
+        match (ev, started) {
+            // Only changes what is drawn, so the simulation never hears of it
+            (Event::Button(Button::Hitboxes), true) => self.show_hitboxes = !self.show_hitboxes,
+            (Event::Button(Button::Hitboxes), false) => (),
+            _ => self.simulation.handle_input(ev, started),
+        }
     }
 
     pub fn handle_mouse_motion(&mut self, x: f32, y: f32) {
-        self.specs_world.fetch_mut::<input::State>()
-            .update_mouse_position(x, y);
+        self.simulation.handle_mouse_motion(x, y);
+    }
+
+    pub fn handle_modifiers(&mut self, modifiers: KeyMods) {
+        self.simulation.handle_modifiers(modifiers);
+    }
+
+    pub fn handle_axis_impulse(&mut self, axis: input::Axis, amount: f32) {
+        self.simulation.handle_axis_impulse(axis, amount);
+    }
+
+    // Keep the camera in step with the window size.
+    pub fn handle_resize(&mut self, width: f32, height: f32) {
+        self.simulation.handle_resize(width, height);
+    }
+
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    pub fn simulation_mut(&mut self) -> &mut Simulation {
+        &mut self.simulation
     }
 }
@@ -0,0 +1,75 @@
+use ddu2020::input::{Axis, Event, State};
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-5
+}
+
+#[test]
+fn held_axes_speed_up_until_they_reach_the_end() {
+    let mut state = State::new();
+    state.update_effect(Event::Axis(Axis::PanX, true), true);
+    assert_eq!(state.get_axis_raw(Axis::PanX), 1.0);
+
+    state.update(0.1);
+    assert!(close(state.get_axis(Axis::PanX), 0.4));
+    state.update(0.1);
+    assert!(close(state.get_axis(Axis::PanX), 0.8));
+    state.update(0.1);
+    assert_eq!(state.get_axis(Axis::PanX), 1.0);
+
+    // The other way goes down to -1.0
+    state.update_effect(Event::Axis(Axis::PanY, false), true);
+    state.update(1.0);
+    assert_eq!(state.get_axis(Axis::PanY), -1.0);
+    assert_eq!(state.get_axis_raw(Axis::PanY), -1.0);
+}
+
+#[test]
+fn let_go_axes_fall_back_to_the_middle() {
+    let mut state = State::new();
+    state.update_effect(Event::Axis(Axis::PanX, true), true);
+    state.update(1.0);
+    state.update_effect(Event::Axis(Axis::PanX, true), false);
+    assert_eq!(state.get_axis_raw(Axis::PanX), 0.0);
+
+    state.update(0.1);
+    assert!(close(state.get_axis(Axis::PanX), 0.7));
+
+    // Without going past it
+    state.update(1.0);
+    assert_eq!(state.get_axis(Axis::PanX), 0.0);
+    state.update(1.0);
+    assert_eq!(state.get_axis(Axis::PanX), 0.0);
+}
+
+#[test]
+fn letting_go_of_one_direction_keeps_the_other_held() {
+    let mut state = State::new();
+    state.update_effect(Event::Axis(Axis::PanX, true), true);
+    state.update_effect(Event::Axis(Axis::PanX, false), true);
+    state.update_effect(Event::Axis(Axis::PanX, true), false);
+    assert_eq!(state.get_axis_raw(Axis::PanX), -1.0);
+
+    state.update_effect(Event::Axis(Axis::PanX, false), false);
+    assert_eq!(state.get_axis_raw(Axis::PanX), 0.0);
+}
+
+#[test]
+fn impulses_push_the_axis_and_wear_off() {
+    let mut state = State::new();
+    assert_eq!(state.get_axis(Axis::Zoom), 0.0);
+
+    state.update_axis_impulse(Axis::Zoom, 0.5);
+    assert_eq!(state.get_axis(Axis::Zoom), 0.5);
+    assert_eq!(state.get_axis_raw(Axis::Zoom), 0.0);
+
+    state.update_axis_impulse(Axis::Zoom, 1.0);
+    assert_eq!(state.get_axis(Axis::Zoom), 1.0);
+    state.update_axis_impulse(Axis::Zoom, -3.0);
+    assert_eq!(state.get_axis(Axis::Zoom), -1.0);
+
+    state.update(0.1);
+    assert!(close(state.get_axis(Axis::Zoom), -0.7));
+    state.update(1.0);
+    assert_eq!(state.get_axis(Axis::Zoom), 0.0);
+}
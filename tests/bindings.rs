@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use ddu2020::input::{create_input_binding, parse_bindings, write_bindings, Binding, BindingError, Event, InputType};
+
+fn bindings(binding: &Binding) -> HashMap<InputType, Event> {
+    binding.bindings().map(|(input, effect)| (*input, *effect)).collect()
+}
+
+#[test]
+fn default_bindings_survive_the_round_trip() {
+    let defaults = create_input_binding();
+    let text = write_bindings(&defaults);
+
+    let read = parse_bindings(&text).unwrap();
+    assert!(!bindings(&defaults).is_empty());
+    assert_eq!(bindings(&read), bindings(&defaults));
+
+    // And writing them again gives the same file
+    assert_eq!(write_bindings(&read), text);
+}
+
+#[test]
+fn comments_and_blank_lines_are_skipped() {
+    let binding = parse_bindings("# Controls\n\n  key Escape Quit  \nwheel Zoom+\n").unwrap();
+    assert_eq!(bindings(&binding).len(), 2);
+}
+
+#[test]
+fn mistakes_are_reported_with_their_line() {
+    let error = |text: &str| parse_bindings(text).err().unwrap();
+
+    match error("key Escape Quit\nkey Esc Quit\n") {
+        BindingError::UnknownKey(2, key) => assert_eq!(key, "Esc"),
+        other => panic!("expected an unknown key, got {:?}", other),
+    }
+    match error("mouse Other Left\n") {
+        BindingError::UnknownMouseButton(1, button) => assert_eq!(button, "Other"),
+        other => panic!("expected an unknown mouse button, got {:?}", other),
+    }
+    match error("# Camera\nkey W PanY\n") {
+        BindingError::UnknownAction(2, action) => assert_eq!(action, "PanY"),
+        other => panic!("expected an unknown action, got {:?}", other),
+    }
+    match error("key W PanY-\nkey S PanY+\nkey W Quit\n") {
+        BindingError::Duplicate(3, input) => assert_eq!(input, "key W"),
+        other => panic!("expected a duplicate binding, got {:?}", other),
+    }
+    match error("wheel\n") {
+        BindingError::BadLine(1, line) => assert_eq!(line, "wheel"),
+        other => panic!("expected a bad line, got {:?}", other),
+    }
+    assert!(matches!(error("key W PanY- extra\n"), BindingError::BadLine(1, _)));
+    assert!(matches!(error("keyboard W Quit\n"), BindingError::BadLine(1, _)));
+}
@@ -0,0 +1,60 @@
+use ddu2020::resources::{Camera, MAX_ZOOM, MIN_ZOOM};
+
+use ggez_goodies::{Point2, Vector2};
+
+fn close(a: Point2, b: Point2) -> bool {
+    (a - b).length() < 0.001
+}
+
+fn camera() -> Camera {
+    Camera {
+        offset: Point2::new(-120.0, 48.5),
+        zoom: 1.5,
+        ..Camera::default()
+    }
+}
+
+#[test]
+fn screen_and_world_positions_round_trip() {
+    let camera = camera();
+
+    for &(x, y) in &[(0.0, 0.0), (400.0, 300.0), (799.0, 12.5), (-30.0, 650.0)] {
+        let screen = Point2::new(x, y);
+        assert!(close(camera.world_to_screen(camera.screen_to_world(screen)), screen));
+        assert!(close(camera.screen_to_world(camera.world_to_screen(screen)), screen));
+    }
+
+    // The top left corner of the screen is the offset, and the world is scaled up by the zoom
+    assert!(close(camera.screen_to_world(Point2::new(0.0, 0.0)), camera.offset));
+    assert!(close(camera.screen_to_world(Point2::new(150.0, 0.0)), camera.offset + Vector2::new(100.0, 0.0)));
+}
+
+#[test]
+fn zooming_keeps_the_point_under_the_cursor() {
+    let mut camera = camera();
+    let cursor = Point2::new(613.0, 97.0);
+    let under_cursor = camera.screen_to_world(cursor);
+
+    for &factor in &[2.0, 0.5, 1.1, 0.9] {
+        camera.zoom_at(cursor, factor);
+        assert!(close(camera.screen_to_world(cursor), under_cursor));
+    }
+
+    // Even when the zoom runs into its limits
+    camera.zoom_at(cursor, 1000.0);
+    assert_eq!(camera.zoom, MAX_ZOOM);
+    assert!(close(camera.screen_to_world(cursor), under_cursor));
+    camera.zoom_at(cursor, 0.0001);
+    assert_eq!(camera.zoom, MIN_ZOOM);
+    assert!(close(camera.screen_to_world(cursor), under_cursor));
+}
+
+#[test]
+fn center_on_puts_the_position_in_the_middle_of_the_screen() {
+    let mut camera = camera();
+    let target = Point2::new(548.0, 214.0);
+
+    camera.center_on(target);
+    assert!(close(camera.center(), target));
+    assert!(close(camera.world_to_screen(target), (camera.screen_size / 2.0).to_point()));
+}
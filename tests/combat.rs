@@ -0,0 +1,95 @@
+mod common;
+
+use common::{bring_enemy_close, player_units};
+use ddu2020::components::*;
+use ddu2020::resources::TICKS_PER_SECOND;
+use ddu2020::world::Simulation;
+
+use specs::{Entity, WorldExt};
+
+// A player unit ordered to attack an enemy that doesn't fight back
+fn attack_passive_enemy(simulation: &mut Simulation) -> (Entity, Entity) {
+    let enemy = bring_enemy_close(simulation);
+    let (attacker, _) = player_units(simulation)[0];
+
+    let world = simulation.specs_world_mut();
+    world.write_storage::<EnemyAi>().remove(enemy);
+    world.write_storage::<Weapon>().remove(enemy);
+    world.write_storage::<Unit>().get_mut(attacker).unwrap().action = Action::attack(enemy);
+
+    (attacker, enemy)
+}
+
+fn distance(simulation: &Simulation, a: Entity, b: Entity) -> f32 {
+    let pos = simulation.specs_world().read_storage::<Position>();
+    (pos.get(a).unwrap().0 - pos.get(b).unwrap().0).length()
+}
+
+fn health(simulation: &Simulation, entity: Entity) -> Option<f32> {
+    simulation.specs_world().read_storage::<Health>().get(entity).map(|health| health.current)
+}
+
+#[test]
+fn attackers_walk_into_range_and_hit_when_ready() {
+    let mut simulation = Simulation::with_seed(&"test.png", 2).unwrap();
+    let (attacker, enemy) = attack_passive_enemy(&mut simulation);
+    assert!(distance(&simulation, attacker, enemy) > WEAPON_RANGE);
+
+    // Every hit is the weapon's damage give or take the spread, and comes a cooldown after the last one
+    let mut hits = Vec::new();
+    let mut last = health(&simulation, enemy).unwrap();
+    for _ in 0..8 * TICKS_PER_SECOND {
+        simulation.update();
+        let current = health(&simulation, enemy).unwrap();
+        if current < last {
+            assert!(distance(&simulation, attacker, enemy) <= WEAPON_RANGE);
+            let damage = last - current;
+            assert!((WEAPON_DAMAGE * (1.0 - DAMAGE_SPREAD)..=WEAPON_DAMAGE * (1.0 + DAMAGE_SPREAD)).contains(&damage));
+            hits.push(simulation.tick());
+        }
+        last = current;
+    }
+
+    assert!(hits.len() >= 4);
+    let cooldown = (WEAPON_COOLDOWN * TICKS_PER_SECOND as f32) as u64;
+    for pair in hits.windows(2) {
+        assert!((pair[1] - pair[0]).abs_diff(cooldown) <= 1);
+    }
+}
+
+#[test]
+fn killed_targets_are_removed_and_the_attacker_stops() {
+    let mut simulation = Simulation::with_seed(&"test.png", 2).unwrap();
+    let (attacker, enemy) = attack_passive_enemy(&mut simulation);
+
+    for _ in 0..20 * TICKS_PER_SECOND {
+        simulation.update();
+    }
+
+    let world = simulation.specs_world();
+    assert!(!world.entities().is_alive(enemy));
+    let unit = world.read_storage::<Unit>();
+    assert!(matches!(unit.get(attacker).unwrap().action, Action::Standby));
+    assert_eq!(world.read_storage::<Motion>().get(attacker).unwrap().velocity.length(), 0.0);
+}
+
+// The way to a target that stays put is only looked for again once in a while
+#[test]
+fn attackers_keep_their_way_to_a_still_target() {
+    let mut simulation = Simulation::with_seed(&"test.png", 2).unwrap();
+    let (attacker, _) = attack_passive_enemy(&mut simulation);
+
+    let repath_in = |simulation: &Simulation| match simulation.specs_world().read_storage::<Unit>().get(attacker).unwrap().action {
+        Action::Attack { repath_in, target_cell, .. } => {
+            assert!(target_cell.is_some());
+            repath_in
+        }
+        _ => panic!("the attacker gave up"),
+    };
+
+    simulation.update();
+    let first = repath_in(&simulation);
+    simulation.update();
+    simulation.update();
+    assert_eq!(repath_in(&simulation), first - 2);
+}
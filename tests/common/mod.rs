@@ -0,0 +1,85 @@
+// Helpers shared by the integration tests. Not every test uses all of them.
+#![allow(dead_code)]
+
+use ddu2020::components::{Health, Motion, Position, Team};
+use ddu2020::input::{self, Button};
+use ddu2020::physics::{add_box_collider, Layers};
+use ddu2020::world::Simulation;
+
+use ggez_goodies::Point2;
+use specs::{Entity, Join, WorldExt};
+
+// Bit patterns of every position and velocity, by entity id.
+pub fn snapshot(simulation: &Simulation) -> Vec<(u32, [u32; 4])> {
+    let world = simulation.specs_world();
+    let (entities, pos, motion) = (
+        world.entities(),
+        world.read_storage::<Position>(),
+        world.read_storage::<Motion>(),
+    );
+
+    (&entities, &pos, &motion).join()
+        .map(|(entity, pos, motion)| (entity.id(), [
+            pos.0.x.to_bits(),
+            pos.0.y.to_bits(),
+            motion.velocity.x.to_bits(),
+            motion.velocity.y.to_bits(),
+        ]))
+        .collect()
+}
+
+// Bit patterns of everyone's health, by entity id.
+pub fn healths(simulation: &Simulation) -> Vec<(u32, u32)> {
+    let world = simulation.specs_world();
+    (&world.entities(), &world.read_storage::<Health>()).join()
+        .map(|(entity, health)| (entity.id(), health.current.to_bits()))
+        .collect()
+}
+
+pub fn player_units(simulation: &Simulation) -> Vec<(Entity, Point2)> {
+    let world = simulation.specs_world();
+    (&world.entities(), &world.read_storage::<Position>(), &world.read_storage::<Team>()).join()
+        .filter(|(_, _, team)| **team == Team::Player)
+        .map(|(entity, pos, _)| (entity, pos.0))
+        .collect()
+}
+
+// Press a button, and let go of it the next tick. Takes two ticks.
+pub fn click(simulation: &mut Simulation, button: Button) {
+    simulation.handle_input(input::Event::Button(button), true);
+    simulation.update();
+    simulation.handle_input(input::Event::Button(button), false);
+    simulation.update();
+}
+
+// Drag a selection box over the whole screen. Takes three ticks.
+pub fn select_all(simulation: &mut Simulation) {
+    simulation.handle_mouse_motion(0.0, 0.0);
+    simulation.handle_input(input::Event::Button(Button::Left), true);
+    simulation.update();
+    simulation.handle_mouse_motion(800.0, 600.0);
+    simulation.update();
+    simulation.handle_input(input::Event::Button(Button::Left), false);
+    simulation.update();
+}
+
+// Select every unit on screen and right click on the given screen position. Takes five ticks.
+pub fn select_all_and_move_to(simulation: &mut Simulation, x: f32, y: f32) {
+    select_all(simulation);
+    simulation.handle_mouse_motion(x, y);
+    click(simulation, Button::Right);
+}
+
+// Put an enemy right next to the player's units, so a fight with random damage starts.
+pub fn bring_enemy_close(simulation: &mut Simulation) -> Entity {
+    let world = simulation.specs_world_mut();
+    let enemy = (&world.entities(), &world.read_storage::<Team>()).join()
+        .find(|(_, team)| **team == Team::Enemy)
+        .map(|(entity, _)| entity)
+        .unwrap();
+
+    world.write_storage::<Position>().get_mut(enemy).unwrap().0 = Point2::new(496.0, 176.0);
+    add_box_collider(enemy, Layers::GROUND, world).unwrap();
+
+    enemy
+}
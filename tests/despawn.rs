@@ -0,0 +1,124 @@
+mod common;
+
+use common::player_units;
+use ddu2020::components::{BoxCollider, Health};
+use ddu2020::resources::{CollisionEvent, CollisionEvents, Contact};
+use ddu2020::physics::{sync_collider, ColliderRes, Layers, Profile};
+use ddu2020::query::SpatialQuery;
+use ddu2020::world::Simulation;
+
+use ggez_goodies::Point2;
+use specs::{Builder, Entity, WorldExt};
+
+fn first_player_unit(simulation: &Simulation) -> (Entity, Point2) {
+    player_units(simulation)[0]
+}
+
+fn hitboxes_at(simulation: &Simulation, point: Point2) -> Vec<Entity> {
+    let world = simulation.specs_world();
+    let (collider, entities) = (world.fetch::<Option<ColliderRes>>(), world.entities());
+    SpatialQuery::new(collider.as_ref().unwrap(), &entities).at_point(point, Layers::ALL)
+}
+
+#[test]
+fn despawn_removes_the_hitbox() {
+    let mut simulation = Simulation::with_seed(&"test.png", 3).unwrap();
+    let (unit, pos) = first_player_unit(&simulation);
+    assert_eq!(hitboxes_at(&simulation, pos), vec![unit]);
+
+    simulation.despawn(unit).unwrap();
+    assert!(hitboxes_at(&simulation, pos).is_empty());
+    assert!(simulation.despawn(unit).is_err());
+
+    // A new entity gets the same index, and its hitbox isn't mistaken for the old one
+    let trigger = simulation.spawn_trigger(pos.x as f64, pos.y as f64, 10.0, 10.0).unwrap();
+    assert_eq!(trigger.id(), unit.id());
+    assert_eq!(hitboxes_at(&simulation, pos), vec![trigger]);
+
+    for _ in 0..60 {
+        simulation.update();
+    }
+}
+
+#[test]
+fn deleting_through_specs_keeps_the_collider_in_sync() {
+    let mut simulation = Simulation::with_seed(&"test.png", 3).unwrap();
+    let (unit, pos) = first_player_unit(&simulation);
+
+    simulation.specs_world_mut().delete_entity(unit).unwrap();
+
+    // Reusing the index before the systems ever run
+    let trigger = simulation.spawn_trigger(pos.x as f64, pos.y as f64, 10.0, 10.0).unwrap();
+    assert_eq!(trigger.id(), unit.id());
+    assert_eq!(hitboxes_at(&simulation, pos), vec![trigger]);
+
+    simulation.update();
+    assert_eq!(hitboxes_at(&simulation, pos), vec![trigger]);
+}
+
+#[test]
+fn dead_units_lose_their_hitbox_the_same_tick() {
+    let mut simulation = Simulation::with_seed(&"test.png", 3).unwrap();
+    let (unit, pos) = first_player_unit(&simulation);
+
+    simulation.specs_world_mut().write_storage::<Health>().get_mut(unit).unwrap().current = 0.0;
+    simulation.update();
+
+    let world = simulation.specs_world();
+    assert!(!world.entities().is_alive(unit));
+    assert!(world.read_storage::<BoxCollider>().get(unit).is_none());
+    assert!(hitboxes_at(&simulation, pos).is_empty());
+}
+
+// Only hitboxes that were added are taken out again, the collider panics on any other
+#[test]
+fn box_colliders_without_a_hitbox_can_be_removed() {
+    let mut simulation = Simulation::with_seed(&"test.png", 3).unwrap();
+    let (unit, pos) = first_player_unit(&simulation);
+
+    let world = simulation.specs_world_mut();
+    let bare = world.create_entity().build();
+    world.write_storage::<BoxCollider>().insert(bare, BoxCollider(Profile::new(bare, Layers::GROUND))).unwrap();
+    world.delete_entity(bare).unwrap();
+
+    // The unit's hitbox goes once, removing its BoxCollider again does nothing
+    world.write_storage::<BoxCollider>().remove(unit);
+    sync_collider(world).unwrap();
+    world.write_storage::<BoxCollider>().remove(unit);
+    sync_collider(world).unwrap();
+
+    simulation.update();
+    assert!(hitboxes_at(&simulation, pos).is_empty());
+}
+
+fn exits(simulation: &Simulation) -> Vec<CollisionEvent> {
+    simulation.specs_world().fetch::<CollisionEvents>().iter()
+        .filter(|event| event.contact == Contact::Exit)
+        .copied()
+        .collect()
+}
+
+// Triggers hear about units that leave them by being removed, not only by walking out
+#[test]
+fn removed_units_leave_their_triggers() {
+    let mut simulation = Simulation::with_seed(&"test.png", 3).unwrap();
+    let units = player_units(&simulation);
+    let ((despawned, despawned_pos), (killed, killed_pos)) = (units[0], units[1]);
+
+    let first = simulation.spawn_trigger(despawned_pos.x as f64, despawned_pos.y as f64, 10.0, 10.0).unwrap();
+    let second = simulation.spawn_trigger(killed_pos.x as f64, killed_pos.y as f64, 10.0, 10.0).unwrap();
+    simulation.update();
+    assert!(exits(&simulation).is_empty());
+
+    simulation.despawn(despawned).unwrap();
+    simulation.update();
+    assert_eq!(exits(&simulation), vec![CollisionEvent { contact: Contact::Exit, first: despawned, second: first }]);
+
+    simulation.specs_world_mut().write_storage::<Health>().get_mut(killed).unwrap().current = 0.0;
+    simulation.update();
+    assert_eq!(exits(&simulation), vec![CollisionEvent { contact: Contact::Exit, first: killed, second }]);
+
+    // Only once
+    simulation.update();
+    assert!(exits(&simulation).is_empty());
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::{bring_enemy_close, healths, select_all_and_move_to, snapshot};
+use ddu2020::world::Simulation;
+
+const TICKS: u64 = 600;
+
+// Select every unit and send it somewhere, then let the fight play out.
+fn run(seed: u64) -> Simulation {
+    let mut simulation = Simulation::with_seed(&"test.png", seed).unwrap();
+
+    select_all_and_move_to(&mut simulation, 700.0, 100.0);
+    while simulation.tick() < TICKS {
+        simulation.update();
+    }
+
+    simulation
+}
+
+#[test]
+fn same_seed_gives_identical_state() {
+    let first = run(1234);
+    let second = run(1234);
+
+    assert_eq!(first.tick(), TICKS);
+    assert_eq!(first.seed(), 1234);
+    assert_eq!(snapshot(&first), snapshot(&second));
+}
+
+#[test]
+fn units_actually_move() {
+    let start = snapshot(&Simulation::with_seed(&"test.png", 1234).unwrap());
+    let end = snapshot(&run(1234));
+
+    assert_ne!(start, end);
+}
+
+fn fight(seed: u64) -> Simulation {
+    let mut simulation = Simulation::with_seed(&"test.png", seed).unwrap();
+
+    bring_enemy_close(&mut simulation);
+    for _ in 0..300 {
+        simulation.update();
+    }
+
+    simulation
+}
+
+#[test]
+fn the_seed_decides_how_fights_go() {
+    let first = fight(1234);
+    assert!(healths(&first).iter().any(|&(_, health)| f32::from_bits(health) < 100.0));
+    assert_eq!(healths(&first), healths(&fight(1234)));
+
+    // Hits do different damage with another seed
+    assert_ne!(healths(&first), healths(&fight(4321)));
+}
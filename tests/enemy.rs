@@ -0,0 +1,90 @@
+use std::env;
+
+use ddu2020::components::*;
+use ddu2020::world::Simulation;
+
+use ggez_goodies::Point2;
+use image::{Rgba, RgbaImage};
+use specs::{Entity, WorldExt};
+
+// An empty level with one enemy, and a player unit the given distance to the right of it
+fn enemy_and_player(name: &str, distance: f32) -> (Simulation, Entity, Entity) {
+    let path = env::temp_dir().join(format!("ddu2020-test-enemy-{}.png", name));
+    RgbaImage::from_pixel(30, 20, Rgba([255, 255, 255, 255])).save(&path).unwrap();
+
+    let mut simulation = Simulation::with_seed(&path, 1).unwrap();
+    let enemy = simulation.spawn_unit(320.0, 320.0, Team::Enemy).unwrap();
+    let player = simulation.spawn_unit(320.0 + distance as f64, 320.0, Team::Player).unwrap();
+
+    (simulation, enemy, player)
+}
+
+fn state(simulation: &Simulation, enemy: Entity) -> (AiState, Action) {
+    let world = simulation.specs_world();
+    let ai = world.read_storage::<EnemyAi>().get(enemy).unwrap().state;
+    let action = world.read_storage::<Unit>().get(enemy).unwrap().action.clone();
+    (ai, action)
+}
+
+fn x(simulation: &Simulation, entity: Entity) -> f32 {
+    simulation.specs_world().read_storage::<Position>().get(entity).unwrap().0.x
+}
+
+#[test]
+fn enemies_leave_players_out_of_sight_alone() {
+    let (mut simulation, enemy, _) = enemy_and_player("out-of-sight", ENEMY_SIGHT_RADIUS + 50.0);
+
+    for _ in 0..60 {
+        simulation.update();
+    }
+
+    assert!(matches!(state(&simulation, enemy), (AiState::Idle, Action::Standby)));
+    assert_eq!(x(&simulation, enemy), 320.0);
+}
+
+#[test]
+fn enemies_chase_and_attack_players_in_sight() {
+    let (mut simulation, enemy, player) = enemy_and_player("chase", ENEMY_SIGHT_RADIUS - 50.0);
+
+    simulation.update();
+    match state(&simulation, enemy) {
+        (AiState::Chase(chased), Action::Attack { target, .. }) => assert!(chased == player && target == player),
+        other => panic!("the enemy isn't going after the player: {:?}", other),
+    }
+
+    for _ in 0..180 {
+        simulation.update();
+    }
+
+    assert!(x(&simulation, player) - x(&simulation, enemy) <= WEAPON_RANGE);
+    let health = simulation.specs_world().read_storage::<Health>().get(player).unwrap().current;
+    assert!(health < 100.0);
+}
+
+#[test]
+fn enemies_give_up_on_players_that_get_away() {
+    let (mut simulation, enemy, player) = enemy_and_player("give-up", ENEMY_SIGHT_RADIUS - 50.0);
+    simulation.update();
+    assert!(matches!(state(&simulation, enemy), (AiState::Chase(_), _)));
+
+    simulation.specs_world_mut().write_storage::<Position>().get_mut(player).unwrap().0 = Point2::new(900.0, 600.0);
+    simulation.update();
+
+    assert!(matches!(state(&simulation, enemy), (AiState::Idle, Action::Standby)));
+    let velocity = simulation.specs_world().read_storage::<Motion>().get(enemy).unwrap().velocity;
+    assert_eq!(velocity.length(), 0.0);
+}
+
+#[test]
+fn enemies_go_back_to_idle_once_their_target_dies() {
+    let (mut simulation, enemy, player) = enemy_and_player("target-dies", ENEMY_SIGHT_RADIUS - 50.0);
+    simulation.update();
+
+    simulation.specs_world_mut().write_storage::<Health>().get_mut(player).unwrap().current = 0.0;
+    for _ in 0..2 {
+        simulation.update();
+    }
+
+    assert!(!simulation.specs_world().entities().is_alive(player));
+    assert!(matches!(state(&simulation, enemy), (AiState::Idle, Action::Standby)));
+}
@@ -0,0 +1,74 @@
+use ddu2020::formation::{arrange, Formation, SPACING};
+
+use ggez_goodies::{Point2, Vector2};
+use specs::{Builder, Entity, World, WorldExt};
+
+// A row of units at the bottom, all sent to the same point above them
+fn units(world: &mut World, count: usize) -> Vec<(Entity, Point2)> {
+    (0..count)
+        .map(|i| (world.create_entity().build(), Point2::new(i as f32 * 40.0, 500.0)))
+        .collect()
+}
+
+// Whether the line segments a-b and c-d cross
+fn crosses(a: Point2, b: Point2, c: Point2, d: Point2) -> bool {
+    let side = |p: Point2, q: Point2, r: Point2| {
+        let (u, v) = (q - p, r - p);
+        u.x * v.y - u.y * v.x
+    };
+    side(a, b, c) * side(a, b, d) < 0.0 && side(c, d, a) * side(c, d, b) < 0.0
+}
+
+#[test]
+fn formations_spread_out_around_the_target() {
+    let target = Point2::new(300.0, 100.0);
+
+    for formation in Formation::ALL.iter() {
+        for count in 1..12 {
+            let mut world = World::new();
+            let units = units(&mut world, count);
+            let arranged = arrange(*formation, &units, target);
+            assert_eq!(arranged.len(), count);
+
+            // Every unit gets its own spot, with room between them
+            for (i, (_, a)) in arranged.iter().enumerate() {
+                for (_, b) in &arranged[i + 1..] {
+                    assert!((*a - *b).length() >= SPACING - 0.01, "{:?} with {} units", formation, count);
+                }
+            }
+
+            // The middle of the formation is where the order was given
+            let middle = arranged.iter().fold(Vector2::zero(), |sum, (_, pos)| sum + pos.to_vector()) / count as f32;
+            assert!((middle - target.to_vector()).length() < 0.01);
+
+            // Nobody walks across anyone else's path
+            let start = |entity: Entity| units.iter().find(|(unit, _)| *unit == entity).unwrap().1;
+            for (i, (a, a_end)) in arranged.iter().enumerate() {
+                for (b, b_end) in &arranged[i + 1..] {
+                    assert!(!crosses(start(*a), *a_end, start(*b), *b_end), "{:?} with {} units", formation, count);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn line_is_across_the_direction_of_travel() {
+    let mut world = World::new();
+    let units = units(&mut world, 5);
+    let arranged = arrange(Formation::Line, &units, Point2::new(80.0, 100.0));
+
+    // Travelling straight up, so the line is level
+    for (_, pos) in &arranged {
+        assert!((pos.y - 100.0).abs() < 0.01);
+    }
+}
+
+#[test]
+fn formations_cycle() {
+    let mut formation = Formation::default();
+    for _ in 0..Formation::ALL.len() {
+        formation = formation.next();
+    }
+    assert_eq!(formation, Formation::default());
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use common::{click, select_all};
+use ddu2020::components::{Position, Unit};
+use ddu2020::input::{self, Button};
+use ddu2020::resources::{Camera, ControlGroups};
+use ddu2020::world::Simulation;
+
+use ggez::event::KeyMods;
+use ggez_goodies::Vector2;
+use specs::{Join, WorldExt};
+
+fn selected(simulation: &Simulation) -> usize {
+    simulation.specs_world().read_storage::<Unit>().join()
+        .filter(|unit| unit.selected)
+        .count()
+}
+
+// Select everything on screen and store it in group 1
+fn assign_group() -> Simulation {
+    let mut simulation = Simulation::with_seed(&"test.png", 3).unwrap();
+
+    select_all(&mut simulation);
+    assert!(selected(&simulation) > 0);
+
+    simulation.handle_modifiers(KeyMods::CTRL);
+    click(&mut simulation, Button::Group1);
+    simulation.handle_modifiers(KeyMods::NONE);
+
+    simulation
+}
+
+#[test]
+fn groups_are_stored_and_recalled() {
+    let mut simulation = assign_group();
+    let count = selected(&simulation);
+
+    // Clicking on nothing clears the selection, the group brings it back
+    click(&mut simulation, Button::Left);
+    assert_eq!(selected(&simulation), 0);
+    click(&mut simulation, Button::Group1);
+    assert_eq!(selected(&simulation), count);
+
+    // An empty group changes nothing
+    click(&mut simulation, Button::Group2);
+    assert_eq!(selected(&simulation), count);
+}
+
+#[test]
+fn double_tap_centres_the_camera() {
+    let mut simulation = assign_group();
+
+    // Look somewhere else first
+    simulation.handle_input(input::Event::Axis(input::Axis::PanX, true), true);
+    for _ in 0..120 {
+        simulation.update();
+    }
+    simulation.handle_input(input::Event::Axis(input::Axis::PanX, true), false);
+    for _ in 0..60 {
+        simulation.update();
+    }
+
+    let group_centre = |simulation: &Simulation| {
+        let world = simulation.specs_world();
+        let (pos, unit) = (world.read_storage::<Position>(), world.read_storage::<Unit>());
+        let positions: Vec<Vector2> = (&pos, &unit).join()
+            .filter(|(_, unit)| unit.selected)
+            .map(|(pos, _)| pos.0.to_vector())
+            .collect();
+        positions.iter().fold(Vector2::zero(), |sum, pos| sum + *pos) / positions.len() as f32
+    };
+    let distance = |simulation: &Simulation| {
+        let centre = simulation.specs_world().fetch::<Camera>().center().to_vector();
+        (centre - group_centre(simulation)).length()
+    };
+
+    let before = distance(&simulation);
+    click(&mut simulation, Button::Group1);
+    assert_eq!(distance(&simulation), before);
+    click(&mut simulation, Button::Group1);
+    assert!(distance(&simulation) < before);
+}
+
+#[test]
+fn groups_survive_saving() {
+    let simulation = assign_group();
+
+    let path = std::env::temp_dir().join("ddu2020_groups_test.txt");
+    simulation.save(&path).unwrap();
+    let loaded = Simulation::load(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    let group_size = |simulation: &Simulation| simulation.specs_world().fetch::<ControlGroups>().groups[0].len();
+    assert!(group_size(&simulation) > 0);
+    assert_eq!(group_size(&loaded), group_size(&simulation));
+}
@@ -0,0 +1,54 @@
+use ddu2020::physics::{Layers, Profile};
+
+use collider::HbProfile;
+use specs::{Builder, World, WorldExt};
+
+#[test]
+fn layers_collide_with_their_defaults() {
+    let mut world = World::new();
+    let mut profile = |layer: Layers| Profile::new(world.create_entity().build(), layer);
+
+    let wall = profile(Layers::WALL);
+    let other_wall = profile(Layers::WALL);
+    let ground = profile(Layers::GROUND);
+    let air = profile(Layers::AIR);
+    let trigger = profile(Layers::TRIGGER);
+    let water = profile(Layers::WATER);
+    let projectile = profile(Layers::PROJECTILE);
+
+    assert!(ground.can_interact(&wall));
+    assert!(ground.can_interact(&trigger));
+    assert!(air.can_interact(&trigger));
+    // Flying units go over walls and units on the ground
+    assert!(!air.can_interact(&wall));
+    assert!(!air.can_interact(&ground));
+    // Only units on the ground are stopped by water
+    assert!(ground.can_interact(&water));
+    assert!(!air.can_interact(&water));
+    assert!(!projectile.can_interact(&water));
+    assert!(projectile.can_interact(&wall));
+    // Walls never collide with each other
+    assert!(!wall.can_interact(&other_wall));
+    assert!(wall.is_wall() && water.is_wall());
+    assert!(!trigger.is_solid());
+}
+
+#[test]
+fn projectiles_miss_whoever_fired_them() {
+    let mut world = World::new();
+    let shooter = world.create_entity().build();
+    let bystander = world.create_entity().build();
+    let projectile = Profile::new(world.create_entity().build(), Layers::PROJECTILE).with_owner(shooter);
+
+    assert!(!projectile.can_interact(&Profile::new(shooter, Layers::GROUND)));
+    assert!(!Profile::new(shooter, Layers::GROUND).can_interact(&projectile));
+    assert!(projectile.can_interact(&Profile::new(bystander, Layers::GROUND)));
+}
+
+#[test]
+fn layer_names_round_trip() {
+    for layers in [Layers::NONE, Layers::GROUND, Layers::WALL | Layers::AIR, Layers::ALL] {
+        assert_eq!(Layers::from_name(&layers.name()), Some(layers));
+    }
+    assert_eq!(Layers::from_name("Ground+Lava"), None);
+}
@@ -0,0 +1,198 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use ddu2020::components::*;
+use ddu2020::level::{load_level, LevelError, Tile, MAX_LEVEL_SIZE, MUD_SPEED, RESOURCE_AMOUNT};
+use ddu2020::pathfinding::NavGrid;
+use ddu2020::physics::setup_collider;
+use ddu2020::world::Simulation;
+
+use ggez_goodies::Point2;
+use image::{Rgba, RgbaImage};
+use specs::{Join, World, WorldExt};
+
+fn world() -> World {
+    let mut world = World::new();
+    register_components(&mut world);
+    setup_collider(&mut world);
+    world
+}
+
+// Absolute paths aren't looked up in the level directory, so tests can make their own levels
+fn temp_level(name: &str, image: &RgbaImage) -> PathBuf {
+    let path = env::temp_dir().join(format!("ddu2020-test-{}.png", name));
+    image.save(&path).unwrap();
+    path
+}
+
+#[test]
+fn missing_and_unreadable_levels() {
+    let mut world = world();
+    assert!(matches!(load_level(&"no such level.png", &mut world, false), Err(LevelError::NotFound(_))));
+
+    let path = env::temp_dir().join("ddu2020-test-garbage.png");
+    fs::write(&path, "not a png").unwrap();
+    assert!(matches!(load_level(&path, &mut world, false), Err(LevelError::Unreadable(..))));
+}
+
+#[test]
+fn unknown_colours_are_only_refused_in_strict_mode() {
+    let mut image = RgbaImage::from_pixel(4, 4, Rgba([255, 255, 255, 255]));
+    image.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+    image.put_pixel(2, 1, Rgba([12, 34, 56, 255]));
+    let path = temp_level("unknown-colour", &image);
+
+    let mut world = world();
+    match load_level(&path, &mut world, true) {
+        Err(LevelError::UnknownColour { x: 2, y: 1, pixel: [12, 34, 56, 255] }) => (),
+        other => panic!("expected an unknown colour, got {:?}", other),
+    }
+    // Nothing is added when it fails
+    assert_eq!(world.entities().join().count(), 0);
+
+    let level = load_level(&path, &mut world, false).unwrap();
+    assert_eq!((level.width, level.height), (4, 4));
+    assert!(world.entities().join().count() > 0);
+
+    // The same goes for whole simulations, whatever kind of build this is
+    assert!(Simulation::with_options(&path, 1, false).is_ok());
+    assert!(matches!(Simulation::with_options(&path, 1, true), Err(LevelError::UnknownColour { .. })));
+}
+
+#[test]
+fn oversized_levels_are_refused() {
+    let path = temp_level("too-large", &RgbaImage::new(MAX_LEVEL_SIZE + 1, 1));
+    match load_level(&path, &mut world(), false) {
+        Err(LevelError::TooLarge { width, height: 1 }) => assert_eq!(width, MAX_LEVEL_SIZE + 1),
+        other => panic!("expected the level to be too large, got {:?}", other),
+    }
+}
+
+#[test]
+fn levels_need_the_collider() {
+    let mut world = World::new();
+    register_components(&mut world);
+    assert!(matches!(load_level(&"test.png", &mut world, false), Err(LevelError::NoCollider)));
+    assert_eq!(world.entities().join().count(), 0);
+}
+
+// One pixel of every colour in the legend, left to right
+const LEGEND: [[u8; 4]; 8] = [
+    [0, 0, 0, 255],
+    [0, 0, 255, 255],
+    [128, 64, 0, 255],
+    [128, 128, 128, 255],
+    [255, 255, 0, 255],
+    [0, 255, 0, 255],
+    [255, 0, 0, 255],
+    [255, 255, 255, 255],
+];
+
+fn legend_level(name: &str) -> PathBuf {
+    let mut image = RgbaImage::from_pixel(8, 2, Rgba([0, 0, 0, 0]));
+    for (x, pixel) in LEGEND.iter().enumerate() {
+        image.put_pixel(x as u32, 0, Rgba(*pixel));
+    }
+    temp_level(name, &image)
+}
+
+#[test]
+fn every_colour_in_the_legend_becomes_its_tile() {
+    let tiles: Vec<Option<Tile>> = LEGEND.iter().map(|pixel| Tile::from_pixel(*pixel)).collect();
+    assert_eq!(tiles, vec![
+        Some(Tile::Wall),
+        Some(Tile::Water),
+        Some(Tile::Mud),
+        Some(Tile::Floor),
+        Some(Tile::Resource),
+        Some(Tile::PlayerSpawn),
+        Some(Tile::EnemySpawn),
+        Some(Tile::Empty),
+    ]);
+    assert_eq!(Tile::from_pixel([0, 0, 0, 0]), Some(Tile::Empty));
+    assert_eq!(Tile::from_pixel([0, 0, 0, 128]), None);
+
+    let mut world = world();
+    let level = load_level(&legend_level("legend"), &mut world, true).unwrap();
+    assert_eq!((level.width, level.height), (8, 2));
+
+    // Terrain is drawn a tile at a time, and only walls and water get a hitbox
+    let (pos, terrain, renderable, box_collider) = (
+        world.read_storage::<Position>(),
+        world.read_storage::<Terrain>(),
+        world.read_storage::<Renderable>(),
+        world.read_storage::<BoxCollider>(),
+    );
+    let drawn: Vec<(Point2, Terrain)> = (&pos, &terrain, &renderable).join()
+        .map(|(pos, terrain, _)| (pos.0, *terrain))
+        .collect();
+    assert_eq!(drawn, vec![
+        (NavGrid::cell_center(0, 0), Terrain::Wall),
+        (NavGrid::cell_center(1, 0), Terrain::Water),
+        (NavGrid::cell_center(2, 0), Terrain::Mud),
+        (NavGrid::cell_center(3, 0), Terrain::Floor),
+    ]);
+    let solid: Vec<Terrain> = (&terrain, &box_collider).join().map(|(terrain, _)| *terrain).collect();
+    assert_eq!(solid, vec![Terrain::Wall, Terrain::Water]);
+
+    let resources: Vec<(Point2, u32)> = (&pos, &world.read_storage::<ResourceNode>()).join()
+        .map(|(pos, resource_node)| (pos.0, resource_node.amount))
+        .collect();
+    assert_eq!(resources, vec![(NavGrid::cell_center(4, 0), RESOURCE_AMOUNT)]);
+
+    let spawn_points: Vec<(Team, Point2)> = level.spawn_points.iter().map(|spawn_point| (spawn_point.team, spawn_point.pos)).collect();
+    assert_eq!(spawn_points, vec![
+        (Team::Player, NavGrid::cell_center(5, 0)),
+        (Team::Enemy, NavGrid::cell_center(6, 0)),
+    ]);
+
+    // Walls and water can't be walked through, mud slows units down
+    let nav_grid = world.fetch::<NavGrid>();
+    let blocked: Vec<bool> = (0..8).map(|x| nav_grid.is_blocked(x, 0)).collect();
+    assert_eq!(blocked, vec![true, true, false, false, false, false, false, false]);
+    let speeds: Vec<f32> = (2..8).map(|x| nav_grid.speed(x, 0)).collect();
+    assert_eq!(speeds, vec![MUD_SPEED, 1.0, 1.0, 1.0, 1.0, 1.0]);
+}
+
+#[test]
+fn units_start_on_their_team_spawn_points() {
+    let simulation = Simulation::with_seed(&legend_level("spawns"), 1).unwrap();
+    let world = simulation.specs_world();
+
+    let enemy_ai = world.read_storage::<EnemyAi>();
+    let (entities, pos, team) = (world.entities(), world.read_storage::<Position>(), world.read_storage::<Team>());
+    let units: Vec<(Point2, Team, bool)> = (&entities, &pos, &team).join()
+        .map(|(entity, pos, team)| (pos.0, *team, enemy_ai.contains(entity)))
+        .collect();
+
+    // Only enemies are controlled by AI
+    assert_eq!(units, vec![
+        (NavGrid::cell_center(5, 0), Team::Player, false),
+        (NavGrid::cell_center(6, 0), Team::Enemy, true),
+    ]);
+}
+
+#[test]
+fn units_walk_through_mud_at_half_speed() {
+    // A strip of mud along the top row
+    let mut image = RgbaImage::from_pixel(20, 4, Rgba([255, 255, 255, 255]));
+    for x in 0..20 {
+        image.put_pixel(x, 0, Rgba([128, 64, 0, 255]));
+    }
+    let mut simulation = Simulation::with_seed(&temp_level("mud", &image), 1).unwrap();
+
+    let in_mud = simulation.spawn_unit(64.0, 0.0, Team::Player).unwrap();
+    let on_ground = simulation.spawn_unit(64.0, 64.0, Team::Player).unwrap();
+    {
+        let mut unit = simulation.specs_world_mut().write_storage::<Unit>();
+        unit.get_mut(in_mud).unwrap().action = Action::goto(vec![Point2::new(500.0, 0.0)]);
+        unit.get_mut(on_ground).unwrap().action = Action::goto(vec![Point2::new(500.0, 64.0)]);
+    }
+    simulation.update();
+
+    let motion = simulation.specs_world().read_storage::<Motion>();
+    let speed = |entity| motion.get(entity).unwrap().velocity.length();
+    assert!((speed(in_mud) - WALK_SPEED * MUD_SPEED).abs() < 0.01);
+    assert!((speed(on_ground) - WALK_SPEED).abs() < 0.01);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::{click, player_units, select_all};
+use ddu2020::components::{Action, Unit};
+use ddu2020::input::Button;
+use ddu2020::physics::{push_weight, resolve_entity_collision};
+use ddu2020::world::Simulation;
+
+use collider::{Hitbox, geom::{Shape, v2}};
+use specs::{Join, WorldExt};
+
+// A unit walking right into another one just to its right
+fn bump(other_speed: f64) -> (Hitbox, Hitbox) {
+    (
+        Shape::circle(20.0).place(v2(100.0, 100.0)).moving(v2(60.0, 0.0)),
+        Shape::circle(20.0).place(v2(119.99, 100.0)).moving(v2(other_speed, 0.0)),
+    )
+}
+
+#[test]
+fn moving_units_nudge_idle_ones_along() {
+    let (walker, idle) = bump(0.0);
+    let (walker, idle) = resolve_entity_collision(walker, idle, push_weight(1.0, false), push_weight(1.0, false));
+
+    assert!((walker.vel.value.x - 30.0).abs() < 0.01);
+    assert!((idle.vel.value.x - 30.0).abs() < 0.01);
+}
+
+#[test]
+fn heavy_units_are_hardly_shoved() {
+    let (walker, heavy) = bump(0.0);
+    let (walker, heavy) = resolve_entity_collision(walker, heavy, push_weight(1.0, false), push_weight(10.0, false));
+
+    assert!(heavy.vel.value.x < 6.0);
+    assert!(walker.vel.value.x < 6.0);
+
+    // But a heavy unit walking into a light one takes it along at nearly full speed
+    let (heavy, light) = bump(0.0);
+    let (heavy, light) = resolve_entity_collision(heavy, light, push_weight(10.0, false), push_weight(1.0, false));
+    assert!(heavy.vel.value.x > 54.0);
+    assert!(light.vel.value.x > 54.0);
+}
+
+#[test]
+fn holding_units_do_not_budge() {
+    let (walker, holder) = bump(0.0);
+    let start = holder.value.pos;
+    let (walker, holder) = resolve_entity_collision(walker, holder, push_weight(1.0, false), push_weight(1.0, true));
+
+    assert_eq!(holder.value.pos, start);
+    assert_eq!(holder.vel.value.x, 0.0);
+    assert_eq!(walker.vel.value.x, 0.0);
+}
+
+#[test]
+fn units_moving_apart_are_left_alone() {
+    let (walker, runner) = bump(80.0);
+    let (walker, runner) = resolve_entity_collision(walker, runner, push_weight(1.0, false), push_weight(1.0, false));
+
+    assert_eq!(walker.vel.value.x, 60.0);
+    assert_eq!(runner.vel.value.x, 80.0);
+}
+
+#[test]
+fn hold_order_keeps_units_in_place() {
+    let mut simulation = Simulation::with_seed(&"test.png", 5).unwrap();
+    let start = player_units(&simulation);
+
+    select_all(&mut simulation);
+    click(&mut simulation, Button::Hold);
+    while simulation.tick() < 120 {
+        simulation.update();
+    }
+
+    let world = simulation.specs_world();
+    let players = world.read_storage::<Unit>().join().filter(|unit| unit.selected).count();
+    assert!(players > 0);
+    for unit in world.read_storage::<Unit>().join().filter(|unit| unit.selected) {
+        assert!(matches!(unit.action, Action::Hold));
+    }
+    assert_eq!(player_units(&simulation), start);
+}
@@ -0,0 +1,91 @@
+use ddu2020::pathfinding::NavGrid;
+
+use ggez_goodies::Point2;
+
+// Every cell a unit walking the path from start would pass through
+fn cells_walked(grid: &NavGrid, start: Point2, path: &[Point2]) -> Vec<(usize, usize)> {
+    let mut cells = Vec::new();
+    let mut from = start;
+
+    for &to in path {
+        let steps = ((to - from).length().ceil() as usize).max(1);
+        for i in 0..=steps {
+            let cell = grid.cell_at(from + (to - from) * (i as f32 / steps as f32)).unwrap();
+            if cells.last() != Some(&cell) {
+                cells.push(cell);
+            }
+        }
+        from = to;
+    }
+
+    cells
+}
+
+#[test]
+fn paths_go_around_walls() {
+    // A wall down the middle with a gap at the bottom
+    let mut grid = NavGrid::new(5, 5);
+    for y in 0..4 {
+        grid.set_blocked(2, y, true);
+    }
+
+    let (start, goal) = (NavGrid::cell_center(0, 0), NavGrid::cell_center(4, 0));
+    let path = grid.find_path(start, goal).unwrap();
+
+    assert_eq!(path.last(), Some(&goal));
+    let cells = cells_walked(&grid, start, &path);
+    assert!(cells.contains(&(2, 4)));
+    assert!(cells.iter().all(|&(x, y)| !grid.is_blocked(x as isize, y as isize)));
+}
+
+#[test]
+fn paths_never_cut_corners() {
+    let mut grid = NavGrid::new(3, 3);
+    grid.set_blocked(1, 0, true);
+
+    // Straight through the diagonal would scrape the wall, so go down first
+    let (start, goal) = (NavGrid::cell_center(0, 0), NavGrid::cell_center(1, 1));
+    assert_eq!(grid.find_path(start, goal), Some(vec![NavGrid::cell_center(0, 1), goal]));
+
+    // Open diagonals are fine
+    let goal = NavGrid::cell_center(1, 2);
+    assert_eq!(grid.find_path(NavGrid::cell_center(0, 1), goal), Some(vec![goal]));
+}
+
+#[test]
+fn no_path_to_walls_or_closed_off_cells() {
+    let mut grid = NavGrid::new(5, 5);
+    grid.set_blocked(2, 2, true);
+    let start = NavGrid::cell_center(0, 0);
+
+    assert_eq!(grid.find_path(start, NavGrid::cell_center(2, 2)), None);
+    assert_eq!(grid.find_path(start, NavGrid::cell_center(9, 9)), None);
+
+    // Walled in on every side
+    for (x, y) in &[(3, 3), (4, 3), (3, 4)] {
+        grid.set_blocked(*x, *y, true);
+    }
+    assert_eq!(grid.find_path(start, NavGrid::cell_center(4, 4)), None);
+}
+
+#[test]
+fn paths_go_around_mud_when_that_is_quicker() {
+    let mut grid = NavGrid::new(5, 3);
+    let (start, goal) = (NavGrid::cell_center(0, 1), NavGrid::cell_center(4, 1));
+
+    // A little slower is still quicker than a detour
+    for x in 1..4 {
+        grid.set_speed(x, 1, 0.9);
+    }
+    assert_eq!(grid.find_path(start, goal), Some(vec![goal]));
+
+    // But deep mud isn't
+    for x in 1..4 {
+        grid.set_speed(x, 1, 0.25);
+    }
+    let path = grid.find_path(start, goal).unwrap();
+    let (last, detour) = path.split_last().unwrap();
+    assert_eq!(*last, goal);
+    assert!(!detour.is_empty());
+    assert!(detour.iter().all(|waypoint| grid.cell_at(*waypoint).unwrap().1 != 1), "walked through mud: {:?}", path);
+}
@@ -0,0 +1,122 @@
+use ddu2020::components::{register_components, ColliderShape, Position};
+use ddu2020::level::load_level;
+use ddu2020::pathfinding::NavGrid;
+use ddu2020::physics::{add_box_collider, add_collider, setup_collider, ColliderRes, Layers, Profile};
+use ddu2020::query::SpatialQuery;
+
+use ggez::graphics::Rect;
+use ggez_goodies::Point2;
+use image::{Rgba, RgbaImage};
+use specs::{Builder, Entity, World, WorldExt};
+
+fn world() -> World {
+    let mut world = World::new();
+    register_components(&mut world);
+    setup_collider(&mut world);
+    world
+}
+
+fn spawn(world: &mut World, shape: ColliderShape, x: f32, y: f32, layer: Layers) -> Entity {
+    let entity = world.create_entity().with(Position(Point2::new(x, y))).with(shape).build();
+    add_box_collider(entity, layer, world).unwrap();
+    entity
+}
+
+#[test]
+fn overlap_queries_find_hitboxes_on_the_layers() {
+    let mut world = world();
+    let unit = spawn(&mut world, ColliderShape::circle(20.0), 100.0, 100.0, Layers::GROUND);
+    let flyer = spawn(&mut world, ColliderShape::circle(20.0), 130.0, 100.0, Layers::AIR);
+    let wall = spawn(&mut world, ColliderShape::rect(32.0, 32.0), 200.0, 100.0, Layers::WALL);
+
+    let (collider, entities) = (world.fetch::<Option<ColliderRes>>(), world.entities());
+    let query = SpatialQuery::new(collider.as_ref().unwrap(), &entities);
+
+    assert_eq!(query.at_point(Point2::new(105.0, 95.0), Layers::ALL), vec![unit]);
+    assert_eq!(query.at_point(Point2::new(105.0, 95.0), Layers::AIR), vec![]);
+    assert_eq!(query.in_circle(Point2::new(115.0, 100.0), 10.0, Layers::ALL), vec![unit, flyer]);
+    assert_eq!(query.in_rect(Rect::new(120.0, 80.0, 100.0, 40.0), Layers::ALL), vec![flyer, wall]);
+    assert_eq!(query.in_rect(Rect::new(120.0, 80.0, 100.0, 40.0), Layers::WALL), vec![wall]);
+}
+
+#[test]
+fn nearest_honours_the_filter_and_radius() {
+    let mut world = world();
+    let near = spawn(&mut world, ColliderShape::circle(20.0), 100.0, 100.0, Layers::GROUND);
+    let far = spawn(&mut world, ColliderShape::circle(20.0), 200.0, 100.0, Layers::GROUND);
+
+    let (collider, entities) = (world.fetch::<Option<ColliderRes>>(), world.entities());
+    let query = SpatialQuery::new(collider.as_ref().unwrap(), &entities);
+    let origin = Point2::new(50.0, 100.0);
+
+    assert_eq!(query.nearest(origin, 300.0, Layers::GROUND, |_| true), Some(near));
+    assert_eq!(query.nearest(origin, 300.0, Layers::GROUND, |entity| entity != near), Some(far));
+    // The far one's hitbox reaches into the radius, but its middle doesn't
+    assert_eq!(query.nearest(origin, 145.0, Layers::GROUND, |entity| entity != near), None);
+}
+
+#[test]
+fn raycast_stops_at_the_first_hitbox() {
+    let mut world = world();
+    let first = spawn(&mut world, ColliderShape::rect(20.0, 20.0), 100.0, 100.0, Layers::WALL);
+    let second = spawn(&mut world, ColliderShape::circle(20.0), 200.0, 100.0, Layers::WALL);
+
+    let (collider, entities) = (world.fetch::<Option<ColliderRes>>(), world.entities());
+    let query = SpatialQuery::new(collider.as_ref().unwrap(), &entities);
+
+    let (hit, at) = query.raycast(Point2::new(0.0, 100.0), Point2::new(300.0, 100.0), Layers::WALL).unwrap();
+    assert_eq!(hit, first);
+    assert!((at.x - 90.0).abs() < 0.01);
+
+    let (hit, at) = query.raycast(Point2::new(300.0, 100.0), Point2::new(0.0, 100.0), Layers::WALL).unwrap();
+    assert_eq!(hit, second);
+    assert!((at.x - 210.0).abs() < 0.01);
+
+    // Passing above both, or stopping short of them
+    assert!(query.line_of_sight(Point2::new(0.0, 50.0), Point2::new(300.0, 50.0)));
+    assert!(query.line_of_sight(Point2::new(0.0, 100.0), Point2::new(80.0, 100.0)));
+    assert!(!query.line_of_sight(Point2::new(150.0, 0.0), Point2::new(50.0, 200.0)));
+}
+
+// Water stops units walking, but not what they can see, like the level legend says
+#[test]
+fn water_doesnt_block_sight() {
+    // A column of water and a column of wall
+    let mut image = RgbaImage::from_pixel(10, 5, Rgba([255, 255, 255, 255]));
+    for y in 0..5 {
+        image.put_pixel(3, y, Rgba([0, 0, 255, 255]));
+        image.put_pixel(6, y, Rgba([0, 0, 0, 255]));
+    }
+    let path = std::env::temp_dir().join("ddu2020-test-water-sight.png");
+    image.save(&path).unwrap();
+
+    let mut world = world();
+    load_level(&path, &mut world, true).unwrap();
+
+    let (collider, entities) = (world.fetch::<Option<ColliderRes>>(), world.entities());
+    let query = SpatialQuery::new(collider.as_ref().unwrap(), &entities);
+    let (left, middle, right) = (NavGrid::cell_center(1, 2), NavGrid::cell_center(5, 2), NavGrid::cell_center(8, 2));
+
+    assert!(!query.at_point(NavGrid::cell_center(3, 2), Layers::WATER).is_empty());
+    assert!(query.line_of_sight(left, middle));
+    assert!(!query.line_of_sight(middle, right));
+    assert!(!query.line_of_sight(left, right));
+}
+
+// The first entity, and anything it owns, are found like any other
+#[test]
+fn queries_find_the_first_entity_and_what_it_owns() {
+    let mut world = world();
+    let first = spawn(&mut world, ColliderShape::circle(20.0), 100.0, 100.0, Layers::GROUND);
+    assert_eq!(first.id(), 0);
+
+    let shot = world.create_entity()
+        .with(Position(Point2::new(110.0, 100.0)))
+        .with(ColliderShape::circle(4.0))
+        .build();
+    add_collider(shot, Profile::new(shot, Layers::PROJECTILE).with_owner(first), &mut world).unwrap();
+
+    let (collider, entities) = (world.fetch::<Option<ColliderRes>>(), world.entities());
+    let query = SpatialQuery::new(collider.as_ref().unwrap(), &entities);
+    assert_eq!(query.in_circle(Point2::new(105.0, 100.0), 10.0, Layers::ALL), vec![first, shot]);
+}
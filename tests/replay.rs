@@ -0,0 +1,60 @@
+mod common;
+
+use common::{select_all_and_move_to, snapshot};
+use ddu2020::input;
+use ddu2020::replay::Replay;
+use ddu2020::world::Simulation;
+
+use ggez::event::KeyMods;
+
+const TICKS: u64 = 300;
+
+#[test]
+fn replay_reproduces_the_match() {
+    let mut simulation = Simulation::with_seed(&"test.png", 99).unwrap();
+    simulation.start_recording();
+
+    select_all_and_move_to(&mut simulation, 612.25, 87.5);
+
+    for tick in simulation.tick()..TICKS {
+        match tick {
+            30 => simulation.handle_input(input::Event::Axis(input::Axis::PanX, true), true),
+            45 => simulation.handle_input(input::Event::Axis(input::Axis::PanX, true), false),
+            50 => simulation.handle_axis_impulse(input::Axis::Zoom, -1.0),
+            // Shift-click, adding to the selection
+            60 => {
+                simulation.handle_modifiers(KeyMods::SHIFT | KeyMods::CTRL);
+                simulation.handle_modifiers(KeyMods::SHIFT);
+                simulation.handle_input(input::Event::Button(input::Button::Left), true);
+            }
+            61 => simulation.handle_input(input::Event::Button(input::Button::Left), false),
+            62 => simulation.handle_modifiers(KeyMods::NONE),
+            _ => (),
+        }
+
+        simulation.update();
+    }
+
+    // Round trip through a file
+    let path = std::env::temp_dir().join("ddu2020_replay_test.txt");
+    simulation.recording().unwrap().save(&path).unwrap();
+    let replay = Replay::load(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(&replay, simulation.recording().unwrap());
+
+    let mut replayed = Simulation::from_replay(replay).unwrap();
+    for _ in 0..TICKS {
+        // Live input is ignored during playback
+        replayed.handle_mouse_motion(1.0, 1.0);
+        replayed.update();
+    }
+
+    assert_eq!(snapshot(&simulation), snapshot(&replayed));
+}
+
+#[test]
+fn broken_replays_are_refused() {
+    assert!("not a replay".parse::<Replay>().is_err());
+    assert!("ddu2020 replay 1\nlevel test.png\nseed 1\n3 button Jump down\n".parse::<Replay>().is_err());
+}
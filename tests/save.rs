@@ -0,0 +1,94 @@
+mod common;
+
+use common::{bring_enemy_close, healths, select_all_and_move_to, snapshot};
+use ddu2020::components::{Action, Unit};
+use ddu2020::resources::Random;
+use ddu2020::world::Simulation;
+
+use specs::{Join, WorldExt};
+
+// Order every player unit somewhere and let them walk for a while.
+fn start_match() -> Simulation {
+    let mut simulation = Simulation::with_seed(&"test.png", 7).unwrap();
+
+    select_all_and_move_to(&mut simulation, 700.0, 100.0);
+    while simulation.tick() < 120 {
+        simulation.update();
+    }
+
+    simulation
+}
+
+#[test]
+fn loaded_game_matches_saved_game() {
+    let mut simulation = start_match();
+
+    let path = std::env::temp_dir().join("ddu2020_save_test.txt");
+    simulation.save(&path).unwrap();
+    let mut loaded = Simulation::load(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(loaded.tick(), simulation.tick());
+    assert_eq!(loaded.seed(), simulation.seed());
+    assert_eq!(loaded.level(), simulation.level());
+    assert_eq!(snapshot(&loaded), snapshot(&simulation));
+
+    // Orders survive the round trip
+    let walking = |simulation: &Simulation| simulation.specs_world().read_storage::<Unit>().join()
+        .filter(|unit| matches!(unit.action, Action::Goto { .. }))
+        .count();
+    assert!(walking(&simulation) > 0);
+    assert_eq!(walking(&loaded), walking(&simulation));
+
+    // The collider picks up where it left off, instead of replaying everything since tick 0
+    for _ in 0..60 {
+        simulation.update();
+        loaded.update();
+    }
+    assert_eq!(snapshot(&loaded), snapshot(&simulation));
+}
+
+// An enemy right next to the player's units, so there's a fight with random damage going on
+fn start_fight() -> Simulation {
+    let mut simulation = Simulation::with_seed(&"test.png", 7).unwrap();
+    bring_enemy_close(&mut simulation);
+    simulation
+}
+
+#[test]
+fn saving_doesnt_change_how_the_game_goes() {
+    let path = std::env::temp_dir().join("ddu2020_save_rng_test.txt");
+    let mut saved = start_fight();
+    let mut unsaved = start_fight();
+    let mut loaded = None;
+
+    for tick in 0..300 {
+        if tick == 100 {
+            saved.save(&path).unwrap();
+            loaded = Some(Simulation::load(&path).unwrap());
+            let _ = std::fs::remove_file(&path);
+        }
+        saved.update();
+        unsaved.update();
+        if let Some(loaded) = &mut loaded {
+            loaded.update();
+        }
+    }
+    let loaded = loaded.unwrap();
+
+    assert!(saved.specs_world().fetch::<Random>().draws() > 0);
+    assert_eq!(healths(&saved), healths(&unsaved));
+    assert_eq!(snapshot(&saved), snapshot(&unsaved));
+    assert_eq!(healths(&loaded), healths(&saved));
+    assert_eq!(snapshot(&loaded), snapshot(&saved));
+}
+
+#[test]
+fn broken_saves_are_refused() {
+    let path = std::env::temp_dir().join("ddu2020_broken_save_test.txt");
+    std::fs::write(&path, "ddu2020 save 1\nlevel test.png\nseed x\n").unwrap();
+    assert!(Simulation::load(&path).is_err());
+    let _ = std::fs::remove_file(&path);
+
+    assert!(Simulation::load("this file does not exist").is_err());
+}
@@ -0,0 +1,83 @@
+use ddu2020::components::{BoxCollider, ColliderShape, Motion, Position};
+use ddu2020::physics::{add_box_collider, ColliderRes, HitboxError, Layers};
+use ddu2020::world::Simulation;
+
+use collider::{HbProfile, geom::ShapeKind};
+use ggez_goodies::{Point2, Vector2};
+use specs::{Builder, Join, WorldExt};
+
+#[test]
+fn shapes_are_placed_off_the_position() {
+    let placed = ColliderShape::circle(10.0).with_offset(Vector2::new(0.0, 5.0)).place(Point2::new(100.0, 100.0));
+    assert_eq!(placed.kind(), ShapeKind::Circle);
+    assert_eq!((placed.pos.x, placed.pos.y), (100.0, 105.0));
+    assert_eq!((placed.dims().x, placed.dims().y), (10.0, 10.0));
+}
+
+// An entity with its hitbox off to one side moves with it, instead of jumping by the offset every tick
+#[test]
+fn offset_hitboxes_move_with_their_entity() {
+    let mut simulation = Simulation::with_seed(&"test.png", 1).unwrap();
+    let offset = Vector2::new(0.0, 10.0);
+
+    // Far outside the level, so there is nothing to bump into
+    let world = simulation.specs_world_mut();
+    let entity = world.create_entity()
+        .with(Position(Point2::new(-1000.0, -1000.0)))
+        .with(Motion { velocity: Vector2::new(60.0, 0.0) })
+        .with(ColliderShape::rect(10.0, 10.0).with_offset(offset))
+        .build();
+    add_box_collider(entity, Layers::GROUND, world).unwrap();
+
+    for _ in 0..60 {
+        simulation.update();
+    }
+
+    let world = simulation.specs_world();
+    let pos = world.read_storage::<Position>().get(entity).unwrap().0;
+    assert!((pos - Point2::new(-940.0, -1000.0)).length() < 0.1);
+
+    let collider = world.fetch::<Option<ColliderRes>>();
+    let hitbox = collider.as_ref().unwrap().get_hitbox(entity.id() as u64).value.pos;
+    assert!((hitbox.x as f32 - (pos.x + offset.x)).abs() < 0.01);
+    assert!((hitbox.y as f32 - (pos.y + offset.y)).abs() < 0.01);
+}
+
+#[test]
+fn units_and_walls_get_their_shapes() {
+    let simulation = Simulation::with_seed(&"test.png", 1).unwrap();
+    let world = simulation.specs_world();
+    let collider = world.fetch::<Option<ColliderRes>>();
+    let collider = collider.as_ref().unwrap();
+
+    // Every hitbox is the shape its entity declares, where the entity is
+    let (pos, shape, box_collider) = (
+        world.read_storage::<Position>(),
+        world.read_storage::<ColliderShape>(),
+        world.read_storage::<BoxCollider>(),
+    );
+    let mut count = 0;
+    for (pos, shape, box_collider) in (&pos, &shape, &box_collider).join() {
+        let expected = shape.place(pos.0);
+        let hitbox = collider.get_hitbox(box_collider.0.id()).value;
+        assert_eq!(hitbox.kind(), expected.kind());
+        assert_eq!(hitbox.dims(), expected.dims());
+        assert_eq!(hitbox.pos, expected.pos);
+        count += 1;
+    }
+    assert_eq!(count, box_collider.join().count());
+}
+
+#[test]
+fn hitboxes_need_a_shape_and_a_position() {
+    let mut simulation = Simulation::with_seed(&"test.png", 1).unwrap();
+    let world = simulation.specs_world_mut();
+
+    let shapeless = world.create_entity().with(Position(Point2::new(0.0, 0.0))).build();
+    let nowhere = world.create_entity().with(ColliderShape::circle(10.0)).build();
+    assert!(matches!(add_box_collider(shapeless, Layers::GROUND, world), Err(HitboxError::NoShape(entity)) if entity == shapeless));
+    assert!(matches!(add_box_collider(nowhere, Layers::GROUND, world), Err(HitboxError::NoPosition(entity)) if entity == nowhere));
+
+    let box_collider = world.read_storage::<BoxCollider>();
+    assert!(box_collider.get(shapeless).is_none() && box_collider.get(nowhere).is_none());
+}
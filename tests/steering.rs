@@ -0,0 +1,97 @@
+mod common;
+
+use common::{click, player_units, select_all_and_move_to};
+use ddu2020::components::{Action, Motion, Position, Team, Unit};
+use ddu2020::input::Button;
+use ddu2020::resources::Camera;
+use ddu2020::world::Simulation;
+
+use ggez_goodies::{Point2, Vector2};
+use image::{Rgba, RgbaImage};
+use specs::{Join, WorldExt};
+
+// Steering keeps the crowd flowing, so everyone sent somewhere gets there
+#[test]
+fn group_arrives_without_stalling() {
+    let mut simulation = Simulation::with_seed(&"test.png", 11).unwrap();
+
+    let walking = |simulation: &Simulation| simulation.specs_world().read_storage::<Unit>().join()
+        .filter(|unit| matches!(unit.action, Action::Goto { .. }))
+        .count();
+
+    select_all_and_move_to(&mut simulation, 500.0, 450.0);
+    assert!(walking(&simulation) > 1);
+
+    while simulation.tick() < 20 * 60 {
+        simulation.update();
+    }
+
+    assert_eq!(walking(&simulation), 0);
+
+    // Nobody ends up standing on top of anyone else
+    let world = simulation.specs_world();
+    let (pos, unit) = (world.read_storage::<Position>(), world.read_storage::<Unit>());
+    let positions: Vec<_> = (&pos, &unit).join().map(|(pos, _)| pos.0).collect();
+    for (i, a) in positions.iter().enumerate() {
+        for b in &positions[i + 1..] {
+            assert!((*a - *b).length() > 10.0);
+        }
+    }
+}
+
+// A unit standing still is still walking when it's sent less than a formation spot away
+#[test]
+fn short_orders_are_followed() {
+    let mut simulation = Simulation::with_seed(&"test.png", 11).unwrap();
+    let (unit, start) = player_units(&simulation).into_iter()
+        .find(|(_, pos)| *pos == Point2::new(512.0, 128.0))
+        .unwrap();
+    let destination = start + Vector2::new(0.0, 20.0);
+
+    let to_screen = |simulation: &Simulation, pos| simulation.specs_world().fetch::<Camera>().world_to_screen(pos);
+    let on_screen = to_screen(&simulation, start);
+    simulation.handle_mouse_motion(on_screen.x, on_screen.y);
+    click(&mut simulation, Button::Left);
+    let on_screen = to_screen(&simulation, destination);
+    simulation.handle_mouse_motion(on_screen.x, on_screen.y);
+    click(&mut simulation, Button::Right);
+
+    for _ in 0..120 {
+        simulation.update();
+    }
+
+    let world = simulation.specs_world();
+    assert!(matches!(world.read_storage::<Unit>().get(unit).unwrap().action, Action::Standby));
+    let pos = world.read_storage::<Position>().get(unit).unwrap().0;
+    assert!((pos - destination).length() < 5.0, "stopped at {:?}", pos);
+}
+
+// Units walking at a slant towards a wall turn along it before they get there
+#[test]
+fn units_turn_away_from_walls_ahead() {
+    // The velocity a unit heading down and to the right is given, with or without a wall to its right
+    let velocity = |wall: bool| {
+        let mut image = RgbaImage::from_pixel(20, 20, Rgba([255, 255, 255, 255]));
+        if wall {
+            for y in 0..20 {
+                image.put_pixel(10, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+        let path = std::env::temp_dir().join(format!("ddu2020-test-steering-wall-{}.png", wall));
+        image.save(&path).unwrap();
+
+        let mut simulation = Simulation::with_seed(&path, 1).unwrap();
+        let unit = simulation.spawn_unit(276.0, 160.0, Team::Player).unwrap();
+        simulation.specs_world_mut().write_storage::<Unit>().get_mut(unit).unwrap().action =
+            Action::goto(vec![Point2::new(500.0, 384.0)]);
+        simulation.update();
+
+        let velocity = simulation.specs_world().read_storage::<Motion>().get(unit).unwrap().velocity;
+        velocity
+    };
+
+    let (free, walled) = (velocity(false), velocity(true));
+    assert!((free.x - free.y).abs() < 0.01);
+    assert!(walled.x < walled.y);
+    assert!((walled.length() - free.length()).abs() < 0.01);
+}
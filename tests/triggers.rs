@@ -0,0 +1,87 @@
+mod common;
+
+use common::{player_units, select_all_and_move_to};
+use ddu2020::components::{Action, Position, Unit};
+use ddu2020::resources::{Camera, CollisionEvents, Contact};
+use ddu2020::world::Simulation;
+
+use ggez_goodies::Vector2;
+use specs::WorldExt;
+
+#[test]
+fn triggers_report_units_walking_in_and_out() {
+    let mut simulation = Simulation::with_seed(&"test.png", 11).unwrap();
+
+    // One area just covering where the units start, and one where they are sent
+    let units = player_units(&simulation);
+    let (x, y) = units.iter().fold((0.0, 0.0), |(x, y), (_, pos)| (x + pos.x, y + pos.y));
+    let (x, y) = (x / units.len() as f32, y / units.len() as f32);
+    let start = simulation.spawn_trigger(x as f64, y as f64, 120.0, 80.0).unwrap();
+
+    let target = simulation.specs_world().fetch::<Camera>().screen_to_world(ggez_goodies::Point2::new(500.0, 450.0));
+    let end = simulation.spawn_trigger(target.x as f64, target.y as f64, 120.0, 120.0).unwrap();
+
+    let mut left_start = Vec::new();
+    let mut entered_end = Vec::new();
+    select_all_and_move_to(&mut simulation, 500.0, 450.0);
+    while simulation.tick() < 20 * 60 {
+        simulation.update();
+
+        let events = simulation.specs_world().fetch::<CollisionEvents>();
+        for (contact, other) in events.involving(start) {
+            if contact == Contact::Exit && !left_start.contains(&other) {
+                left_start.push(other);
+            }
+        }
+        for (contact, other) in events.involving(end) {
+            if contact == Contact::Enter && !entered_end.contains(&other) {
+                entered_end.push(other);
+            }
+        }
+    }
+
+    // Everyone walked out of the first area and into the second one
+    for (unit, _) in &units {
+        assert!(left_start.contains(unit));
+        assert!(entered_end.contains(unit));
+    }
+
+    // Nobody was stopped by the triggers, and the triggers didn't move
+    let world = simulation.specs_world();
+    for (_, pos) in player_units(&simulation) {
+        assert!((pos - target).length() < 120.0);
+    }
+    assert_eq!(world.read_storage::<Position>().get(end).unwrap().0, target);
+}
+
+// A unit pushing against another one that won't budge doesn't report touching it over and over
+#[test]
+fn pushing_doesnt_repeat_contacts() {
+    let mut simulation = Simulation::with_seed(&"test.png", 2).unwrap();
+    let units = player_units(&simulation);
+    let (walker, walker_pos) = units[0];
+    let (holder, holder_pos) = units[1];
+    assert_eq!(holder_pos - walker_pos, Vector2::new(32.0, 0.0));
+
+    {
+        let world = simulation.specs_world_mut();
+        let mut unit = world.write_storage::<Unit>();
+        unit.get_mut(holder).unwrap().action = Action::Hold;
+        unit.get_mut(walker).unwrap().action = Action::goto(vec![holder_pos + Vector2::new(8.0, 0.0)]);
+    }
+
+    let mut contacts = Vec::new();
+    for _ in 0..120 {
+        simulation.update();
+        let events = simulation.specs_world().fetch::<CollisionEvents>();
+        contacts.extend(events.involving(walker).filter(|(_, other)| *other == holder));
+    }
+    assert!(contacts.is_empty(), "{:?}", contacts);
+
+    // It really was pushing the whole time
+    let units = player_units(&simulation);
+    let position = |entity| units.iter().find(|(unit, _)| *unit == entity).unwrap().1;
+    assert!(((position(holder) - position(walker)).length() - 20.0).abs() < 0.1);
+    assert_eq!(position(holder), holder_pos);
+    assert!(matches!(simulation.specs_world().read_storage::<Unit>().get(walker).unwrap().action, Action::Goto { .. }));
+}
@@ -0,0 +1,74 @@
+use ddu2020::components::{BoxCollider, Position, Renderable, Terrain};
+use ddu2020::level::{merge_walls, Tile, WallRect, TILE_SIZE};
+use ddu2020::physics::{ColliderRes, Layers};
+use ddu2020::query::SpatialQuery;
+use ddu2020::world::Simulation;
+
+use ggez_goodies::Point2;
+use specs::{Join, WorldExt};
+
+// # is a wall, ~ is water
+fn tiles(rows: &[&str]) -> Vec<Tile> {
+    rows.iter()
+        .flat_map(|row| row.chars())
+        .map(|c| match c {
+            '#' => Tile::Wall,
+            '~' => Tile::Water,
+            _ => Tile::Empty,
+        })
+        .collect()
+}
+
+#[test]
+fn walls_merge_into_rectangles() {
+    let level = tiles(&[
+        "####",
+        "#..#",
+        "#~~#",
+        "####",
+    ]);
+    let rect = |x, y, w, h, terrain| WallRect { x, y, w, h, terrain };
+
+    assert_eq!(merge_walls(&level, 4), vec![
+        rect(0, 0, 4, 1, Terrain::Wall),
+        rect(0, 1, 1, 3, Terrain::Wall),
+        rect(3, 1, 1, 3, Terrain::Wall),
+        rect(1, 2, 2, 1, Terrain::Water),
+        rect(1, 3, 2, 1, Terrain::Wall),
+    ]);
+
+    // A solid block is a single rectangle
+    assert_eq!(merge_walls(&tiles(&["###", "###"]), 3), vec![rect(0, 0, 3, 2, Terrain::Wall)]);
+    assert_eq!(merge_walls(&tiles(&["...", "..."]), 3), vec![]);
+}
+
+#[test]
+fn every_wall_tile_is_still_solid() {
+    let simulation = Simulation::with_seed(&"test.png", 1).unwrap();
+    let world = simulation.specs_world();
+    let (collider, entities) = (world.fetch::<Option<ColliderRes>>(), world.entities());
+    let query = SpatialQuery::new(collider.as_ref().unwrap(), &entities);
+
+    let (position, renderable, terrain) = (
+        world.read_storage::<Position>(),
+        world.read_storage::<Renderable>(),
+        world.read_storage::<Terrain>(),
+    );
+    let walls: Vec<Point2> = (&position, &renderable, &terrain).join()
+        .filter(|(_, _, terrain)| matches!(terrain, Terrain::Wall | Terrain::Water))
+        .map(|(pos, _, _)| pos.0)
+        .collect();
+    assert!(!walls.is_empty());
+
+    // Anywhere on a tile, not just its middle
+    for pos in &walls {
+        for corner in &[(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0), (0.0, 0.0)] {
+            let point = Point2::new(pos.x + corner.0 * (TILE_SIZE / 2.0 - 1.0), pos.y + corner.1 * (TILE_SIZE / 2.0 - 1.0));
+            assert!(!query.at_point(point, Layers::WALL | Layers::WATER).is_empty(), "no wall at {:?}", point);
+        }
+    }
+
+    // With fewer hitboxes than tiles
+    let wall_hitboxes = world.read_storage::<BoxCollider>().join().filter(|b| b.0.is_wall()).count();
+    assert!(wall_hitboxes < walls.len());
+}